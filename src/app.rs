@@ -0,0 +1,74 @@
+//! The navigation stack. Every screen is a [`Page`], the top of the stack gets the input.
+
+use std::io::Stdout;
+
+use crossterm::event::KeyEvent;
+use speki_backend::card::CardCache;
+
+use crate::backend::{get_input, Input};
+
+pub enum PageAction {
+    /// Stay on the current page.
+    None,
+    /// Open a new page on top of the current one.
+    Push(Box<dyn Page>),
+    /// Close the current page and go back to the one below.
+    Pop,
+    /// Close the current page and open another one in its place.
+    Replace(Box<dyn Page>),
+}
+
+pub trait Page {
+    fn render(&mut self, stdout: &mut Stdout, cache: &mut CardCache);
+
+    fn handle_key(
+        &mut self,
+        stdout: &mut Stdout,
+        key: KeyEvent,
+        cache: &mut CardCache,
+    ) -> PageAction;
+
+    /// Called when the page above this one was popped and this one is on top again.
+    fn on_resume(&mut self, _stdout: &mut Stdout, _cache: &mut CardCache) {}
+}
+
+pub struct App {
+    pages: Vec<Box<dyn Page>>,
+}
+
+impl App {
+    pub fn new(root: Box<dyn Page>) -> Self {
+        Self { pages: vec![root] }
+    }
+
+    /// Runs until the last page is popped.
+    pub fn run(&mut self, stdout: &mut Stdout, cache: &mut CardCache) {
+        while let Some(page) = self.pages.last_mut() {
+            page.render(stdout, cache);
+            let key = match get_input() {
+                Input::Key(key) => key,
+                // Rendering again picks up the new terminal size.
+                Input::Resize => continue,
+            };
+            let action = page.handle_key(stdout, key, cache);
+            self.apply(stdout, action, cache);
+        }
+    }
+
+    fn apply(&mut self, stdout: &mut Stdout, action: PageAction, cache: &mut CardCache) {
+        match action {
+            PageAction::None => {}
+            PageAction::Push(page) => self.pages.push(page),
+            PageAction::Pop => {
+                self.pages.pop();
+                if let Some(page) = self.pages.last_mut() {
+                    page.on_resume(stdout, cache);
+                }
+            }
+            PageAction::Replace(page) => {
+                self.pages.pop();
+                self.pages.push(page);
+            }
+        }
+    }
+}
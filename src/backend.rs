@@ -1,8 +1,14 @@
 use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use speki_backend::card::{Card, CardCache, SavedCard};
 use speki_backend::categories::Category;
 use speki_backend::common::randvec;
+use speki_backend::config::Config;
+use speki_backend::git::git_save;
 
 use speki_backend::paths::get_share_path;
 use speki_backend::Id;
@@ -13,46 +19,84 @@ use crossterm::event::KeyEvent;
 
 use crossterm::event::{read, Event, KeyCode};
 
-pub fn get_text_from_vim(
+use crate::cloze;
+
+/// A file in the temp folder that's removed again when it's dropped, however the editing went.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    /// The name has the time in it and an existing file is never written over, so no one else
+    /// using the temp folder can guess it or put their own file in its place.
+    fn create(name: &str, contents: &str) -> std::io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path =
+            std::env::temp_dir().join(format!("speki-{}-{:x}-{}", std::process::id(), nanos, name));
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let temp = Self(path);
+        file.write_all(contents.as_bytes())?;
+        Ok(temp)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Opens the text in `$EDITOR`, or nvim if it's not set, and returns it as it was saved.
+///
+/// The vimrc is only passed on to vim and nvim.
+pub fn get_text_from_editor(
     initial_text: Option<String>,
     vim_rc: Option<&str>,
 ) -> std::io::Result<Option<String>> {
-    use std::io::Read;
-    use std::path::Path;
     use std::process::Command;
 
-    let temp_file_path = "first line is front, second line is back";
-    let vimrc_temp_path = "flashcard_vimrc.vim";
-
-    if let Some(text) = initial_text {
-        std::fs::write(temp_file_path, text)?;
+    let card_file = TempFile::create("card.txt", &initial_text.unwrap_or_default())?;
+
+    // Like `code --wait`, the editor can come with arguments.
+    let editor = std::env::var("EDITOR").unwrap_or_default();
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("nvim");
+    let is_vim = Path::new(program)
+        .file_name()
+        .is_some_and(|name| name == "vim" || name == "nvim");
+    let vim_rc = vim_rc.filter(|_| is_vim);
+
+    let mut command = Command::new(program);
+    command.args(args);
+
+    let vimrc_file = vim_rc
+        .map(|vim_rc| TempFile::create("vimrc.vim", vim_rc))
+        .transpose()?;
+    if let Some(vimrc_file) = &vimrc_file {
+        command
+            .arg("-u")
+            .arg(&vimrc_file.0)
+            .arg("-c")
+            .arg("startinsert");
     }
 
-    let mut command = Command::new("nvim");
+    command.arg(&card_file.0).status()?;
 
-    if let Some(vim_rc) = vim_rc {
-        std::fs::write(vimrc_temp_path, vim_rc)?;
-        command.arg("-u").arg(vimrc_temp_path);
-    }
-
-    command
-        .arg("-c")
-        .arg("startinsert")
-        .arg(temp_file_path)
-        .status()?;
-
-    // Cleanup the temporary vimrc file after Vim exits
-    if vim_rc.is_some() {
-        std::fs::remove_file(vimrc_temp_path)?;
+    if !card_file.0.exists() {
+        return Ok(None);
     }
+    std::fs::read_to_string(&card_file.0).map(Some)
+}
 
-    Ok(Path::new(temp_file_path).exists().then_some({
-        let mut file = std::fs::File::open(temp_file_path)?;
-        let mut str_contents = String::new();
-        file.read_to_string(&mut str_contents)?;
-        std::fs::remove_file(temp_file_path)?;
-        str_contents
-    }))
+/// Commits the cards, and pushes them if the config has a git remote. If the config can't be
+/// read they're only committed, so nothing gets lost.
+pub fn save_cards() {
+    let has_remote = Config::load().is_ok_and(|config| config.git_remote.is_some());
+    git_save(has_remote);
 }
 
 pub fn to_ascii_tree(
@@ -84,14 +128,17 @@ pub fn to_ascii_tree(
 
     visited.remove(id);
 
-    Node(card.front_text().to_owned(), children)
+    Node(
+        cloze::question(card.front_text(), card.back_text()),
+        children,
+    )
 }
 
 pub fn cards_as_string(cards: &Vec<SavedCard>) -> String {
     let mut s = String::new();
 
     for card in cards {
-        s.push_str(card.front_text());
+        s.push_str(&cloze::question(card.front_text(), card.back_text()));
         s.push('\n');
     }
     s
@@ -103,19 +150,44 @@ pub fn import_stuff(cache: &mut CardCache) {
         return;
     }
     let category = Category::import_category();
-    let cards = Card::import_cards(import_path.as_path());
-
-    if let Some(cards) = cards {
-        for card in cards {
-            card.save_new_card(&category, cache);
-        }
-    }
+    import_cards(import_path.as_path(), &category, cache);
     let to_path = get_share_path().join("imported.txt");
     std::fs::rename(import_path, to_path).unwrap();
 }
 
-pub fn should_exit(key: &KeyCode) -> bool {
-    matches!(key, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q'))
+/// Returns how many cards were imported, or `None` if the file couldn't be read.
+pub fn import_cards(path: &Path, category: &Category, cache: &mut CardCache) -> Option<usize> {
+    let cards = Card::import_cards(path)?;
+    let qty = cards.len();
+    for card in cards {
+        card.save_new_card(category, cache);
+    }
+    Some(qty)
+}
+
+/// Finds a category by its full name, as printed by [`Category::print_full`].
+pub fn find_category(name: &str) -> Option<Category> {
+    let name = name.trim_matches('/');
+    Category::load_all().ok()?.into_iter().find(|category| {
+        category
+            .print_full()
+            .trim_matches('/')
+            .eq_ignore_ascii_case(name)
+    })
+}
+
+/// Cards due for review in the category and its subcategories, or in all of them.
+pub fn get_due_cards(category: Option<&Category>, cache: &mut CardCache) -> Vec<Id> {
+    let categories = match category {
+        Some(category) => category.get_following_categories(),
+        None => Category::load_all().unwrap_or_default(),
+    };
+
+    let mut cards = BTreeSet::new();
+    for category in &categories {
+        cards.extend(category.get_review_cards(cache));
+    }
+    cards.into_iter().collect()
 }
 
 pub fn get_following_unfinished_cards(category: &Category, cache: &mut CardCache) -> Vec<Id> {
@@ -130,19 +202,18 @@ pub fn get_following_unfinished_cards(category: &Category, cache: &mut CardCache
 pub type CardsFromCategory = Box<dyn FnMut(&Category, &mut CardCache) -> Vec<Id>>;
 //pub type SortCards = Box<dyn FnMut(&mut Vec<&SavedCard>, &mut CardCache)>;
 
-pub fn get_keycode() -> KeyCode {
-    loop {
-        match read().unwrap() {
-            Event::Key(KeyEvent { code, .. }) => return code,
-            _ => continue,
-        }
-    }
+/// The events the pages react to, everything else from crossterm is dropped.
+pub enum Input {
+    Key(KeyEvent),
+    /// The terminal was resized, whatever is on screen has to be drawn again.
+    Resize,
 }
 
-pub fn get_key_event() -> KeyEvent {
+pub fn get_input() -> Input {
     loop {
         match read().unwrap() {
-            Event::Key(event) => return event,
+            Event::Key(event) => return Input::Key(event),
+            Event::Resize(..) => return Input::Resize,
             _ => continue,
         }
     }
@@ -159,3 +230,20 @@ pub fn _get_char() -> char {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_files_get_their_own_names_and_are_removed() {
+        let first = TempFile::create("card.txt", "front").unwrap();
+        let second = TempFile::create("card.txt", "front").unwrap();
+        assert_ne!(first.0, second.0);
+        assert_eq!(std::fs::read_to_string(&first.0).unwrap(), "front");
+
+        let path = first.0.clone();
+        drop(first);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,157 @@
+//! Subcommands for using speki without the menus, e.g. from scripts or a shell prompt.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use speki_backend::card::{Card, CardCache};
+use speki_backend::categories::Category;
+
+use crate::app::Page;
+use crate::backend::{find_category, get_due_cards, import_cards, save_cards};
+use crate::cloze;
+use crate::pages::reviewcards::ReviewPage;
+use crate::queue::Order;
+use crate::stats::{CardStats, Stats, StatsReport};
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Review the due cards of a category, then the pending ones.
+    Review {
+        category: String,
+        /// Review for this many minutes instead, picking the cards worth the most per second.
+        #[arg(long)]
+        minutes: Option<u64>,
+        /// The order to review in, instead of the one in the config file.
+        #[arg(long, value_enum, conflicts_with = "minutes")]
+        order: Option<Order>,
+    },
+    /// Add a card without opening an editor.
+    Add {
+        #[arg(long)]
+        front: String,
+        #[arg(long, default_value = "")]
+        back: String,
+        #[arg(long)]
+        category: String,
+    },
+    /// Print statistics about the whole collection.
+    Stats {
+        /// Print as json, along with the stats of every reviewed card.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Import cards from a file, into the import folder unless a category is given.
+    Import {
+        file: PathBuf,
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// List the cards that are due for review.
+    Due {
+        /// Only look in this category and its subcategories.
+        category: Option<String>,
+        /// Print how many cards are due instead of listing them.
+        #[arg(long)]
+        count: bool,
+        /// Print the due cards and their stats as json, or `{"count": n}` with `--count`.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn category_or_exit(name: &str) -> Category {
+    match find_category(name) {
+        Some(category) => category,
+        None => {
+            eprintln!("no category named '{}'", name);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/// Runs the command, returning the page to open if it needs the interactive ui.
+pub fn run(command: Command, cache: &mut CardCache) -> Option<Box<dyn Page>> {
+    match command {
+        Command::Review {
+            category,
+            minutes,
+            order,
+        } => {
+            let category = category_or_exit(&category);
+            let page = match (minutes, order) {
+                (Some(minutes), _) => {
+                    ReviewPage::timeboxed(category, Duration::from_secs(minutes * 60))
+                }
+                (None, Some(order)) => ReviewPage::ordered(category, order),
+                (None, None) => ReviewPage::normal(category),
+            };
+            return Some(Box::new(page));
+        }
+        Command::Add {
+            front,
+            back,
+            category,
+        } => {
+            let category = category_or_exit(&category);
+            let card = Card::new_simple(front, back).save_new_card(&category, cache);
+            println!("{}", card.id());
+            save_cards();
+        }
+        Command::Stats { json: false } => println!("{}", Stats::collect(cache)),
+        Command::Stats { json: true } => print_json(&StatsReport::collect(cache)),
+        Command::Import { file, category } => {
+            let category = match category {
+                Some(name) => category_or_exit(&name),
+                None => Category::import_category(),
+            };
+            match import_cards(&file, &category, cache) {
+                Some(qty) => {
+                    println!("imported {} cards", qty);
+                    save_cards();
+                }
+                None => {
+                    eprintln!("couldn't import cards from {}", file.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Due {
+            category,
+            count,
+            json,
+        } => {
+            let category = category.map(|name| category_or_exit(&name));
+            let cards = get_due_cards(category.as_ref(), cache);
+            if count && json {
+                print_json(&serde_json::json!({ "count": cards.len() }));
+            } else if count {
+                println!("{}", cards.len());
+            } else if json {
+                let cards: Vec<CardStats> = cards
+                    .iter()
+                    .filter_map(|card| CardStats::new(&cache.get_ref(card)))
+                    .collect();
+                print_json(&cards);
+            } else {
+                for card in cards {
+                    let card = cache.get_ref(&card);
+                    println!("{}", cloze::question(card.front_text(), card.back_text()));
+                }
+            }
+        }
+    }
+    None
+}
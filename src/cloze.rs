@@ -0,0 +1,376 @@
+//! Cloze deletions, written as `{{c1::answer}}` or `{{c1::answer::hint}}` in the front of a card.
+//!
+//! A note with clozes is saved as one card per cloze index. Every card keeps the whole note as
+//! its front, and its back starts with a `{{cN #note}}` line saying which cloze it asks for and
+//! which note it was made from. Cards saved before notes had ids have a bare `{{cN}}`.
+
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use speki_backend::card::{Card, CardCache, SavedCard};
+
+use crate::undo::Change;
+
+enum Segment<'a> {
+    Text(&'a str),
+    Cloze {
+        index: u32,
+        answer: &'a str,
+        hint: Option<&'a str>,
+    },
+}
+
+/// Parses a single `{{cN::answer}}` or `{{cN::answer::hint}}` at the start of `text`,
+/// returning it with its length.
+fn parse_cloze(text: &str) -> Option<(Segment<'_>, usize)> {
+    let rest = text.strip_prefix("{{c")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let index = rest[..digits].parse().ok()?;
+    let rest = rest[digits..].strip_prefix("::")?;
+    let end = rest.find("}}")?;
+
+    let (answer, hint) = match rest[..end].split_once("::") {
+        Some((answer, hint)) => (answer, Some(hint)),
+        None => (&rest[..end], None),
+    };
+
+    let len = text.len() - rest.len() + end + 2;
+    Some((
+        Segment::Cloze {
+            index,
+            answer,
+            hint,
+        },
+        len,
+    ))
+}
+
+fn parse(mut text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+
+    while !text.is_empty() {
+        let Some(start) = text.find("{{c") else {
+            segments.push(Segment::Text(text));
+            break;
+        };
+
+        match parse_cloze(&text[start..]) {
+            Some((cloze, len)) => {
+                if start > 0 {
+                    segments.push(Segment::Text(&text[..start]));
+                }
+                segments.push(cloze);
+                text = &text[start + len..];
+            }
+            None => {
+                // Not a cloze after all, keep it as it is.
+                segments.push(Segment::Text(&text[..start + 3]));
+                text = &text[start + 3..];
+            }
+        }
+    }
+
+    segments
+}
+
+/// The cloze indices used in the text.
+pub fn indices(text: &str) -> BTreeSet<u32> {
+    parse(text)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Cloze { index, .. } => Some(index),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+/// The first line of the back of a card asking for cloze `index` of `note`.
+pub fn marker(index: u32, note: &str) -> String {
+    format!("{{{{c{} #{}}}}}", index, note)
+}
+
+/// The cloze index and the note id, if it has one, from the marker on the first line of the back.
+fn parse_marker(back: &str) -> Option<(u32, Option<&str>)> {
+    let first_line = back.lines().next()?.trim();
+    let inner = first_line.strip_prefix("{{c")?.strip_suffix("}}")?;
+    let (index, note) = match inner.split_once(" #") {
+        Some((index, note)) => (index, Some(note)),
+        None => (inner, None),
+    };
+    Some((index.parse().ok()?, note))
+}
+
+/// Which cloze the card asks for, from the marker on the first line of its back.
+pub fn index(back: &str) -> Option<u32> {
+    parse_marker(back).map(|(index, _)| index)
+}
+
+/// Which note the card was made from.
+fn note(back: &str) -> Option<&str> {
+    parse_marker(back)?.1
+}
+
+fn new_note_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{:x}", nanos)
+}
+
+/// What was written on the back below the marker.
+fn extra(back: &str) -> &str {
+    back.split_once('\n')
+        .map(|(_, extra)| extra.trim())
+        .unwrap_or_default()
+}
+
+/// Fills in every cloze with its answer, except `index`, which is blanked out when `blank` is set.
+fn render(text: &str, index: u32, blank: bool) -> String {
+    parse(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Cloze {
+                index: idx, hint, ..
+            } if idx == index && blank => format!("[{}]", hint.unwrap_or("...")),
+            Segment::Cloze { answer, .. } => answer.to_string(),
+        })
+        .collect()
+}
+
+/// The question and answer to show for a card, with the gap blanked out of a cloze card's front
+/// and filled back in on its back.
+pub fn display(front: &str, back: &str) -> (String, String) {
+    let Some(index) = index(back) else {
+        return (front.to_string(), back.to_string());
+    };
+
+    let mut answer = render(front, index, false);
+    let extra = extra(back);
+    if !extra.is_empty() {
+        answer.push_str("\n\n");
+        answer.push_str(extra);
+    }
+
+    (render(front, index, true), answer)
+}
+
+/// The front as it's asked, with a cloze card's gap blanked out. For showing a card outside of a
+/// review, where the raw `{{c1::...}}` would give the answer away.
+pub fn question(front: &str, back: &str) -> String {
+    display(front, back).0
+}
+
+/// What a typed answer should be, the clozed out text for a cloze card and the back otherwise.
+pub fn expected_answer(front: &str, back: &str) -> String {
+    let Some(index) = index(back) else {
+        return back.to_string();
+    };
+
+    parse(front)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Cloze {
+                index: idx, answer, ..
+            } if idx == index => Some(answer),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The back for a card asking for cloze `index`, with the rest of what was written on the back.
+fn back_for(index: u32, note: &str, extra: &str) -> String {
+    if extra.is_empty() {
+        marker(index, note)
+    } else {
+        format!("{}\n{}", marker(index, note), extra)
+    }
+}
+
+/// The front and back of each card of a note, one per cloze index. Empty if the front has no
+/// clozes.
+pub fn note_cards(front: &str, back: &str) -> Vec<(String, String)> {
+    let note = new_note_id();
+    indices(front)
+        .into_iter()
+        .map(|index| (front.to_string(), back_for(index, &note, back)))
+        .collect()
+}
+
+/// After a cloze card's note was edited, gives the new note to the cards made from the same note,
+/// adds cards for new clozes and deletes the ones for clozes that were removed. If the edited
+/// card's own cloze was removed it takes one that has no card, becomes a plain card when no clozes
+/// are left, and is deleted otherwise.
+///
+/// Records `change`, which should hold the edited card from before the edit, so that undoing it
+/// reverts the edit and the sync together.
+pub fn sync_siblings(
+    old_front: &str,
+    edited: &SavedCard,
+    mut change: Change,
+    cache: &mut CardCache,
+) {
+    let new_front = edited.front_text();
+    let own_index = match index(edited.back_text()) {
+        Some(own_index) if new_front != old_front => own_index,
+        _ => return change.record(),
+    };
+
+    let note_id = note(edited.back_text());
+    let siblings: Vec<SavedCard> = SavedCard::load_all_cards()
+        .into_iter()
+        .filter(|card| {
+            let back = card.back_text();
+            card.id() != edited.id()
+                && index(back).is_some()
+                && match note_id {
+                    Some(note_id) => note(back) == Some(note_id),
+                    // Made before notes had ids, so going by what the notes look like.
+                    None => {
+                        note(back).is_none()
+                            && card.category() == edited.category()
+                            && card.front_text() == old_front
+                    }
+                }
+        })
+        .collect();
+    // Notes without an id get one now.
+    let note_id = note_id.map(str::to_owned).unwrap_or_else(new_note_id);
+
+    for sibling in &siblings {
+        change = change.with_neighbours(sibling.id(), cache);
+    }
+
+    let wanted = indices(new_front);
+    let mut present = BTreeSet::new();
+    if wanted.contains(&own_index) {
+        present.insert(own_index);
+    }
+
+    for sibling in siblings {
+        let idx = index(sibling.back_text()).unwrap();
+        let mut sibling = cache.get_owned(sibling.id());
+        if wanted.contains(&idx) && present.insert(idx) {
+            sibling.set_front_text(new_front);
+            let back = back_for(idx, &note_id, extra(sibling.back_text()));
+            if back != sibling.back_text() {
+                sibling.set_back_text(&back);
+            }
+        } else {
+            sibling.delete(cache);
+        }
+    }
+
+    let extra = extra(edited.back_text()).to_owned();
+    let own_index = if present.contains(&own_index) {
+        Some(own_index)
+    } else {
+        wanted.difference(&present).next().copied()
+    };
+    let mut own = cache.get_owned(edited.id());
+    match own_index {
+        Some(own_index) => {
+            present.insert(own_index);
+            let back = back_for(own_index, &note_id, &extra);
+            if back != own.back_text() {
+                own.set_back_text(&back);
+            }
+        }
+        None if wanted.is_empty() => own.set_back_text(&extra),
+        None => {
+            change = change.with_neighbours(edited.id(), cache);
+            own.delete(cache);
+        }
+    }
+
+    for idx in wanted.difference(&present) {
+        let card = Card::new_simple(new_front.to_string(), back_for(*idx, &note_id, &extra))
+            .save_new_card(edited.category(), cache);
+        change.created(card.as_path());
+    }
+
+    change.record();
+    cache.refresh();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn makes_a_card_per_index_of_one_note() {
+        let cards = note_cards("{{c2::b}} {{c1::a}} {{c2::c}}", "extra");
+        assert_eq!(cards.len(), 2);
+        assert_eq!(index(&cards[0].1), Some(1));
+        assert_eq!(index(&cards[1].1), Some(2));
+        assert_eq!(note(&cards[0].1), note(&cards[1].1));
+        assert_eq!(extra(&cards[1].1), "extra");
+        assert!(note_cards("no clozes", "back").is_empty());
+    }
+
+    #[test]
+    fn finds_the_indices() {
+        let front = "{{c1::Oslo}} is the capital of {{c2::Norway::a country}}, {{c1::Oslo}} again";
+        assert_eq!(indices(front), BTreeSet::from([1, 2]));
+        assert!(indices("no clozes here").is_empty());
+    }
+
+    #[test]
+    fn leaves_what_isnt_a_cloze() {
+        assert!(indices("{{c}} {{cx::a}} {{c1:a}} {{c1::unclosed").is_empty());
+        let (question, _) = display("{{c::a}} and {{c1::b}}", "{{c1}}");
+        assert_eq!(question, "{{c::a}} and [...]");
+    }
+
+    #[test]
+    fn reads_the_marker() {
+        let back = format!("{}\nmore on the back", marker(3, "1a2b"));
+        assert_eq!(index(&back), Some(3));
+        assert_eq!(note(&back), Some("1a2b"));
+        assert_eq!(extra(&back), "more on the back");
+
+        // From before notes had ids.
+        assert_eq!(index("{{c2}}"), Some(2));
+        assert_eq!(note("{{c2}}"), None);
+
+        assert_eq!(index("just an answer"), None);
+        assert_eq!(index(""), None);
+    }
+
+    #[test]
+    fn blanks_out_only_the_asked_cloze() {
+        let front = "{{c1::Oslo}} is the capital of {{c2::Norway::a country}}";
+        let (question, answer) = display(front, &back_for(2, "n", "in Europe"));
+        assert_eq!(question, "Oslo is the capital of [a country]");
+        assert_eq!(answer, "Oslo is the capital of Norway\n\nin Europe");
+
+        let (question, answer) = display(front, &marker(1, "n"));
+        assert_eq!(question, "[...] is the capital of Norway");
+        assert_eq!(answer, "Oslo is the capital of Norway");
+    }
+
+    #[test]
+    fn questions_dont_give_the_answer_away() {
+        let question = question("{{c1::Oslo}} is in {{c2::Norway}}", &marker(2, "n"));
+        assert_eq!(question, "Oslo is in [...]");
+        assert_eq!(super::question("plain", "back"), "plain");
+    }
+
+    #[test]
+    fn shows_plain_cards_as_they_are() {
+        assert_eq!(
+            display("front", "back"),
+            ("front".to_string(), "back".to_string())
+        );
+        assert_eq!(expected_answer("front", "back"), "back");
+    }
+
+    #[test]
+    fn expects_every_gap_of_the_index() {
+        let front = "{{c1::red}}, {{c2::green}} and {{c1::blue}}";
+        assert_eq!(expected_answer(front, &marker(1, "n")), "red, blue");
+        assert_eq!(expected_answer(front, &marker(2, "n")), "green");
+    }
+}
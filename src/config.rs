@@ -0,0 +1,159 @@
+//! Settings that only concern the tui.
+//!
+//! They're read from the same `config.toml` as [`speki_backend::config::Config`], as tables
+//! like `[keys]` and `[review]` next to the backend's own settings.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use speki_backend::paths::get_share_path;
+
+use crate::queue::Order;
+use crate::theme::ThemeName;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Action name to the key or keys that trigger it, see [`crate::keymap`].
+    pub keys: HashMap<String, Keys>,
+    pub limits: Limits,
+    pub review: ReviewSettings,
+    pub editor: EditorSettings,
+    /// `dark`, `light` or `high_contrast`, see [`crate::theme`].
+    pub theme: ThemeName,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct EditorSettings {
+    /// Write cards in `$EDITOR`, or nvim if it's not set, instead of the built-in editor.
+    pub external: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ReviewSettings {
+    /// How the normal review mode orders the cards.
+    pub order: Order,
+    /// Start review sessions with typed answers on.
+    pub typed_answers: bool,
+    /// Leave a card out of a session that has its reverse in it.
+    pub separate_reverse_cards: bool,
+}
+
+/// Caps on how many cards get reviewed a day, see [`crate::limits`].
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Limits {
+    /// Reviews of cards that were already learned, across every category.
+    pub reviews: Option<usize>,
+    /// Pending cards seen for the first time, across every category.
+    pub new: Option<usize>,
+    /// Caps for a category, like `math/algebra`, and its subcategories.
+    pub categories: HashMap<String, Limit>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Limit {
+    pub reviews: Option<usize>,
+    pub new: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Keys {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Keys::One(key) => std::slice::from_ref(key),
+            Keys::Many(keys) => keys,
+        }
+    }
+}
+
+impl TuiConfig {
+    /// The backend's config file.
+    pub fn path() -> PathBuf {
+        get_share_path().join("config.toml")
+    }
+
+    pub fn load() -> Result<Self, String> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        toml::from_str(&contents).map_err(|e| format!("invalid {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(not(test))]
+fn cell() -> &'static OnceLock<TuiConfig> {
+    static CONFIG: OnceLock<TuiConfig> = OnceLock::new();
+    &CONFIG
+}
+
+/// Every test runs on its own thread, so giving each thread its own config lets tests set one
+/// without racing the others.
+#[cfg(test)]
+fn cell() -> &'static OnceLock<TuiConfig> {
+    thread_local! {
+        static CONFIG: &'static OnceLock<TuiConfig> = Box::leak(Box::default());
+    }
+    CONFIG.with(|config| *config)
+}
+
+/// Must be called at startup, before the first call to [`config`].
+pub fn set_config(config: TuiConfig) {
+    assert!(
+        cell().set(config).is_ok(),
+        "the config was read before it was set"
+    );
+}
+
+pub fn config() -> &'static TuiConfig {
+    cell().get_or_init(TuiConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_the_file_with_the_backend() {
+        let contents = r#"
+git_remote = "git@example.com:me/cards.git"
+theme = "high_contrast"
+
+[keys]
+quit = ["q", "esc"]
+help = "?"
+
+[review]
+typed_answers = true
+
+[limits]
+reviews = 100
+"#;
+        let backend: speki_backend::config::Config = toml::from_str(contents).unwrap();
+        assert_eq!(
+            backend.git_remote.as_deref(),
+            Some("git@example.com:me/cards.git")
+        );
+
+        let tui: TuiConfig = toml::from_str(contents).unwrap();
+        assert!(matches!(tui.theme, ThemeName::HighContrast));
+        assert_eq!(tui.keys["quit"].as_slice(), ["q", "esc"]);
+        assert_eq!(tui.keys["help"].as_slice(), ["?"]);
+        assert!(tui.review.typed_answers);
+        assert_eq!(tui.limits.reviews, Some(100));
+    }
+}
@@ -0,0 +1,160 @@
+//! Char level diff between a typed answer and the one on the card.
+//!
+//! Case and surrounding whitespace don't count, in the diff or in the suggested grade.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Edit {
+    /// In both.
+    Same(char),
+    /// In the answer but missing from what was typed.
+    Missing(char),
+    /// Typed but not in the answer.
+    Extra(char),
+}
+
+fn same(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Lines up `typed` with `expected` along their longest common subsequence.
+pub fn diff(typed: &str, expected: &str) -> Vec<Edit> {
+    let typed: Vec<char> = typed.trim().chars().collect();
+    let expected: Vec<char> = expected.trim().chars().collect();
+    let (n, m) = (typed.len(), expected.len());
+
+    // lcs[i][j] is the length of the longest common subsequence of typed[i..] and expected[j..].
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same(typed[i], expected[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(typed[i], expected[j]) {
+            edits.push(Edit::Same(typed[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            edits.push(Edit::Extra(typed[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Missing(expected[j]));
+            j += 1;
+        }
+    }
+    edits.extend(typed[i..].iter().map(|c| Edit::Extra(*c)));
+    edits.extend(expected[j..].iter().map(|c| Edit::Missing(*c)));
+    edits
+}
+
+/// Levenshtein distance, in chars that aren't [`same`].
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + !same(ca, *cb) as usize;
+            row[j + 1] = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        prev = row;
+    }
+
+    prev[b.len()]
+}
+
+/// The grade key to suggest for a typed answer, going by how much of it is off.
+pub fn suggest_grade(typed: &str, expected: &str) -> char {
+    let (typed, expected) = (typed.trim(), expected.trim());
+    let longest = typed.chars().count().max(expected.chars().count()).max(1);
+    let off = edit_distance(typed, expected) as f32 / longest as f32;
+
+    if off == 0. {
+        '4'
+    } else if off <= 0.1 {
+        '3'
+    } else if off <= 0.3 {
+        '2'
+    } else {
+        '1'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(edits: &[Edit]) -> String {
+        edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Same(c) | Edit::Extra(c) => Some(*c),
+                Edit::Missing(_) => None,
+            })
+            .collect()
+    }
+
+    fn expected(edits: &[Edit]) -> String {
+        edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Same(c) | Edit::Missing(c) => Some(*c),
+                Edit::Extra(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_keeps_both_texts() {
+        let edits = diff("kitten", "sitting");
+        assert_eq!(typed(&edits), "kitten");
+        assert_eq!(expected(&edits), "sitting");
+    }
+
+    #[test]
+    fn diff_marks_what_is_missing_and_extra() {
+        assert_eq!(
+            diff("cat", "cart"),
+            vec![
+                Edit::Same('c'),
+                Edit::Same('a'),
+                Edit::Missing('r'),
+                Edit::Same('t')
+            ]
+        );
+        assert_eq!(diff("ab", ""), vec![Edit::Extra('a'), Edit::Extra('b')]);
+        assert_eq!(diff("", "ab"), vec![Edit::Missing('a'), Edit::Missing('b')]);
+    }
+
+    #[test]
+    fn diff_ignores_case_and_surrounding_whitespace() {
+        let edits = diff("  Paris ", "paris");
+        assert!(edits.iter().all(|edit| matches!(edit, Edit::Same(_))));
+        assert_eq!(typed(&edits), "Paris");
+        assert_eq!(suggest_grade("  Paris ", "paris"), '4');
+    }
+
+    #[test]
+    fn edit_distance_counts_chars() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("Ærø", "ærø"), 0);
+    }
+
+    #[test]
+    fn suggested_grade_goes_by_how_much_is_off() {
+        assert_eq!(suggest_grade("photosynthesis", "photosynthesis"), '4');
+        assert_eq!(suggest_grade("photosynthesys", "photosynthesis"), '3');
+        assert_eq!(suggest_grade("fotosynthesys", "photosynthesis"), '2');
+        assert_eq!(suggest_grade("no idea", "photosynthesis"), '1');
+        assert_eq!(suggest_grade("", ""), '4');
+    }
+}
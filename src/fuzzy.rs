@@ -0,0 +1,116 @@
+//! Fuzzy matching for the filter in the pickers.
+
+const MATCH: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 24;
+const WORD_START_BONUS: i64 = 20;
+const GAP_PENALTY: i64 = 2;
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    match index.checked_sub(1).map(|prev| chars[prev]) {
+        None => true,
+        Some(prev) => {
+            !prev.is_alphanumeric() || (prev.is_lowercase() && chars[index].is_uppercase())
+        }
+    }
+}
+
+/// Matches the chars of `query` in order, anywhere in `candidate`, ignoring case.
+///
+/// Returns the score, higher is better, and the char indices in `candidate` that matched.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some((0, vec![]));
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let lowered: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let mut best: Option<(i64, Vec<usize>)> = None;
+
+    // Try every place the first char shows up and keep the best scoring run from there.
+    for start in (0..lowered.len()).filter(|&idx| lowered[idx] == query[0]) {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+
+        for &wanted in &query[1..] {
+            let Some(found) = (next..lowered.len()).find(|&idx| lowered[idx] == wanted) else {
+                break;
+            };
+            positions.push(found);
+            next = found + 1;
+        }
+
+        if positions.len() != query.len() {
+            // If it didn't fit from here it won't fit from any later start either.
+            break;
+        }
+
+        let score = score(&chars, &positions);
+        if !matches!(&best, Some((best, _)) if *best >= score) {
+            best = Some((score, positions));
+        }
+    }
+
+    best
+}
+
+fn score(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+
+    for &pos in positions {
+        score += MATCH;
+        if is_word_start(chars, pos) {
+            score += WORD_START_BONUS;
+        }
+        match prev {
+            Some(prev) if pos == prev + 1 => score += CONSECUTIVE_BONUS,
+            Some(prev) => score -= GAP_PENALTY * (pos - prev - 1) as i64,
+            None => score -= pos as i64,
+        }
+        prev = Some(pos);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_of(query: &str, candidate: &str) -> i64 {
+        fuzzy_match(query, candidate).unwrap().0
+    }
+
+    #[test]
+    fn matches_chars_in_order_ignoring_case() {
+        assert_eq!(fuzzy_match("rvw", "Review").unwrap().1, [0, 2, 5]);
+        assert_eq!(fuzzy_match("REV", "review").unwrap().1, [0, 1, 2]);
+        assert!(fuzzy_match("wr", "review").is_none());
+        assert!(fuzzy_match("x", "").is_none());
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+    }
+
+    #[test]
+    fn prefers_runs_and_word_starts() {
+        assert!(score_of("add", "add card") > score_of("add", "a dead dog"));
+        assert!(score_of("vc", "view cards") > score_of("vc", "overcast"));
+        assert!(score_of("card", "cards") > score_of("card", "my cards"));
+        // camelCase humps count as word starts.
+        assert_eq!(fuzzy_match("cc", "cramCards").unwrap().1, [0, 4]);
+    }
+
+    #[test]
+    fn picks_the_best_start() {
+        // The second `s` starts a word, so it beats the first.
+        assert_eq!(fuzzy_match("st", "best stats").unwrap().1, [5, 6]);
+    }
+}
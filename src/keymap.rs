@@ -0,0 +1,721 @@
+//! Every key command the pages react to, with their default keys and the user's overrides.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::Keys;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    GenerateAnswer,
+    FixQuestion,
+    FixQuestionAndAnswer,
+    ShowInfo,
+    QuickPriority,
+    SetPriority,
+    MarkFinished,
+    Suspend,
+    AddTag,
+    AddDependency,
+    AddDependent,
+    Merge,
+    ViewDependencies,
+    MoveCard,
+    EditCard,
+    Reveal,
+    Skip,
+    OpenBrowser,
+    DependencyTree,
+    DependentTree,
+    NewDependency,
+    NewDependent,
+    AddCard,
+    Delete,
+    Help,
+    Quit,
+    NextCard,
+    PrevCard,
+    CopyCard,
+    AddCardWithAi,
+    RemoveFromList,
+    Search,
+    Undo,
+    TypeAnswer,
+    AcceptGrade,
+    GradeNone,
+    GradeLate,
+    GradeSome,
+    GradePerfect,
+    AddReverse,
+    ScrollLeft,
+    ScrollRight,
+    ScrollDown,
+    ScrollUp,
+    ListDown,
+    ListUp,
+    ListPageDown,
+    ListPageUp,
+    ListTop,
+    ListBottom,
+    Filter,
+    Select,
+}
+
+impl Action {
+    /// The grade a grading action gives, as the digit a [`speki_backend::card::Grade`] is
+    /// parsed from.
+    pub fn grade(self) -> Option<char> {
+        match self {
+            Action::GradeNone => Some('1'),
+            Action::GradeLate => Some('2'),
+            Action::GradeSome => Some('3'),
+            Action::GradePerfect => Some('4'),
+            _ => None,
+        }
+    }
+}
+
+/// The pages that dispatch through the keymap.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    Review,
+    Browse,
+    Cram,
+    Menu,
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Review => write!(f, "review"),
+            Scope::Browse => write!(f, "browse"),
+            Scope::Cram => write!(f, "cram"),
+            Scope::Menu => write!(f, "menu"),
+        }
+    }
+}
+
+struct ActionInfo {
+    action: Action,
+    /// What the action is called in the config file.
+    name: &'static str,
+    /// Shown in the help overlay.
+    description: &'static str,
+    scopes: &'static [Scope],
+    keys: &'static [&'static str],
+}
+
+const ALL: &[Scope] = &[Scope::Review, Scope::Browse, Scope::Cram, Scope::Menu];
+/// Every page that shows a card.
+const CARD: &[Scope] = &[Scope::Review, Scope::Browse, Scope::Cram];
+const BOTH: &[Scope] = &[Scope::Review, Scope::Browse];
+const REVIEW: &[Scope] = &[Scope::Review];
+const REVIEW_OR_CRAM: &[Scope] = &[Scope::Review, Scope::Cram];
+const BROWSE: &[Scope] = &[Scope::Browse];
+const MENU: &[Scope] = &[Scope::Menu];
+
+const fn info(
+    action: Action,
+    name: &'static str,
+    description: &'static str,
+    scopes: &'static [Scope],
+    keys: &'static [&'static str],
+) -> ActionInfo {
+    ActionInfo {
+        action,
+        name,
+        description,
+        scopes,
+        keys,
+    }
+}
+
+const ACTIONS: &[ActionInfo] = &[
+    info(
+        Action::GenerateAnswer,
+        "generate_answer",
+        "generate the answer with openai",
+        CARD,
+        &["H"],
+    ),
+    info(
+        Action::FixQuestion,
+        "fix_question",
+        "rewrite the question with openai",
+        CARD,
+        &["u"],
+    ),
+    info(
+        Action::FixQuestionAndAnswer,
+        "fix_question_and_answer",
+        "rewrite both question and answer with openai",
+        CARD,
+        &["U"],
+    ),
+    info(
+        Action::ShowInfo,
+        "show_info",
+        "show recall, stability and strength",
+        CARD,
+        &["`"],
+    ),
+    info(
+        Action::QuickPriority,
+        "quick_priority",
+        "set priority from the next key pressed",
+        CARD,
+        &["p"],
+    ),
+    info(
+        Action::SetPriority,
+        "set_priority",
+        "type in a priority from 0 to 100",
+        CARD,
+        &["P"],
+    ),
+    info(
+        Action::MarkFinished,
+        "mark_finished",
+        "mark the card as finished",
+        CARD,
+        &["f"],
+    ),
+    info(Action::Suspend, "suspend", "suspend the card", CARD, &["S"]),
+    info(Action::AddTag, "add_tag", "tag the card", CARD, &["g"]),
+    info(
+        Action::AddDependency,
+        "add_dependency",
+        "pick an existing dependency",
+        CARD,
+        &["y"],
+    ),
+    info(
+        Action::AddDependent,
+        "add_dependent",
+        "pick an existing dependent",
+        CARD,
+        &["t"],
+    ),
+    info(
+        Action::Merge,
+        "merge",
+        "merge with another card",
+        CARD,
+        &["M"],
+    ),
+    info(
+        Action::ViewDependencies,
+        "view_dependencies",
+        "list dependencies and dependents",
+        CARD,
+        &["v"],
+    ),
+    info(
+        Action::MoveCard,
+        "move_card",
+        "move the card to another folder",
+        CARD,
+        &["m"],
+    ),
+    info(Action::EditCard, "edit_card", "edit the card", CARD, &["e"]),
+    info(
+        Action::AddReverse,
+        "add_reverse",
+        "add a card with the front and back swapped",
+        CARD,
+        &["R"],
+    ),
+    info(
+        Action::ScrollLeft,
+        "scroll_left",
+        "scroll code blocks to the left",
+        CARD,
+        &["<"],
+    ),
+    info(
+        Action::ScrollRight,
+        "scroll_right",
+        "scroll code blocks to the right",
+        CARD,
+        &[">"],
+    ),
+    info(
+        Action::ScrollDown,
+        "scroll_down",
+        "scroll the back of a long card down",
+        CARD,
+        &["j", "down"],
+    ),
+    info(
+        Action::ScrollUp,
+        "scroll_up",
+        "scroll the back of a long card up",
+        CARD,
+        &["k", "up"],
+    ),
+    info(
+        Action::Reveal,
+        "reveal",
+        "show the answer",
+        REVIEW_OR_CRAM,
+        &["space"],
+    ),
+    info(
+        Action::GradeNone,
+        "grade_none",
+        "grade 1, didn't remember",
+        REVIEW_OR_CRAM,
+        &["1"],
+    ),
+    info(
+        Action::GradeLate,
+        "grade_late",
+        "grade 2, remembered too late",
+        REVIEW_OR_CRAM,
+        &["2"],
+    ),
+    info(
+        Action::GradeSome,
+        "grade_some",
+        "grade 3, remembered with some effort",
+        REVIEW_OR_CRAM,
+        &["3"],
+    ),
+    info(
+        Action::GradePerfect,
+        "grade_perfect",
+        "grade 4, remembered perfectly",
+        REVIEW_OR_CRAM,
+        &["4"],
+    ),
+    info(
+        Action::AcceptGrade,
+        "accept_grade",
+        "take the suggested grade for a typed answer",
+        REVIEW,
+        &["enter"],
+    ),
+    info(Action::Skip, "skip", "skip to the next card", CARD, &["s"]),
+    info(
+        Action::OpenBrowser,
+        "open_browser",
+        "browse all cards",
+        REVIEW,
+        &["o"],
+    ),
+    info(
+        Action::DependencyTree,
+        "dependency_tree",
+        "pick from the dependency tree",
+        BOTH,
+        &["X"],
+    ),
+    info(
+        Action::DependentTree,
+        "dependent_tree",
+        "pick from the dependent tree",
+        BOTH,
+        &["x"],
+    ),
+    info(
+        Action::NewDependency,
+        "new_dependency",
+        "write a new dependency",
+        BOTH,
+        &["Y"],
+    ),
+    info(
+        Action::NewDependent,
+        "new_dependent",
+        "write a new dependent",
+        BOTH,
+        &["T"],
+    ),
+    info(
+        Action::AddCard,
+        "add_card",
+        "add a new card to the same folder",
+        BOTH,
+        &["a"],
+    ),
+    info(Action::Delete, "delete", "delete the card", BOTH, &["D"]),
+    info(
+        Action::ListDown,
+        "list_down",
+        "move the selection down",
+        MENU,
+        &["j", "down"],
+    ),
+    info(
+        Action::ListUp,
+        "list_up",
+        "move the selection up",
+        MENU,
+        &["k", "up"],
+    ),
+    info(
+        Action::ListPageDown,
+        "list_page_down",
+        "move the selection a screen down",
+        MENU,
+        &["pagedown"],
+    ),
+    info(
+        Action::ListPageUp,
+        "list_page_up",
+        "move the selection a screen up",
+        MENU,
+        &["pageup"],
+    ),
+    info(
+        Action::ListTop,
+        "list_top",
+        "jump to the top, letters are pressed twice like gg",
+        MENU,
+        &["g", "home"],
+    ),
+    info(
+        Action::ListBottom,
+        "list_bottom",
+        "jump to the bottom",
+        MENU,
+        &["G", "end"],
+    ),
+    info(
+        Action::Filter,
+        "filter",
+        "filter the list, typing any key the list doesn't use starts it too",
+        MENU,
+        &["/"],
+    ),
+    info(
+        Action::Select,
+        "select",
+        "choose the selected item",
+        MENU,
+        &["enter", "space"],
+    ),
+    info(Action::Help, "help", "show this help", ALL, &["?"]),
+    info(Action::Quit, "quit", "go back", ALL, &["q", "Q", "esc"]),
+    info(
+        Action::TypeAnswer,
+        "type_answer",
+        "toggle typing in the answer before it's shown",
+        REVIEW,
+        &["w"],
+    ),
+    info(
+        Action::Undo,
+        "undo",
+        "undo the last change to a card",
+        ALL,
+        &["ctrl-z"],
+    ),
+    info(
+        Action::NextCard,
+        "next_card",
+        "next card",
+        BROWSE,
+        &["l", "right"],
+    ),
+    info(
+        Action::PrevCard,
+        "prev_card",
+        "previous card",
+        BROWSE,
+        &["h", "left"],
+    ),
+    info(
+        Action::CopyCard,
+        "copy_card",
+        "add a new card starting from this one",
+        BROWSE,
+        &["i"],
+    ),
+    info(
+        Action::AddCardWithAi,
+        "add_card_ai",
+        "add a new card and fill it in with openai",
+        BROWSE,
+        &["A"],
+    ),
+    info(
+        Action::RemoveFromList,
+        "remove_from_list",
+        "remove the card from this list",
+        BROWSE,
+        &["r"],
+    ),
+    info(
+        Action::Search,
+        "search",
+        "search for a card",
+        BROWSE,
+        &["/"],
+    ),
+];
+
+/// A key with its modifiers. Shift is dropped for chars since it's already in the char.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<&KeyEvent> for Key {
+    fn from(event: &KeyEvent) -> Self {
+        let mut modifiers = event.modifiers;
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+
+        // A lone "-" is a key, not a separator.
+        while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => modifiers.insert(KeyModifiers::CONTROL),
+                "alt" => modifiers.insert(KeyModifiers::ALT),
+                _ => return Err(format!("unknown modifier '{}' in key '{}'", modifier, s)),
+            }
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "esc" => KeyCode::Esc,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" => KeyCode::Delete,
+                key => match key.strip_prefix('f').and_then(|num| num.parse().ok()) {
+                    Some(num) => KeyCode::F(num),
+                    None => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(num) => write!(f, "f{}", num),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+pub struct KeyMap {
+    bindings: HashMap<Action, Vec<Key>>,
+}
+
+impl KeyMap {
+    /// The default keys with the overrides from the config file applied.
+    ///
+    /// Fails on unknown actions or keys, and if two actions on the same page share a key.
+    pub fn new(overrides: &HashMap<String, Keys>) -> Result<Self, String> {
+        let mut bindings = HashMap::new();
+
+        for info in ACTIONS {
+            let keys = info.keys.iter().map(|key| key.parse().unwrap()).collect();
+            bindings.insert(info.action, keys);
+        }
+
+        for (name, keys) in overrides {
+            let Some(info) = ACTIONS.iter().find(|info| info.name == name) else {
+                return Err(format!("unknown action '{}' in keybindings", name));
+            };
+            let keys = keys
+                .as_slice()
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<Vec<Key>, String>>()?;
+            bindings.insert(info.action, keys);
+        }
+
+        let keymap = Self { bindings };
+        keymap.check_conflicts()?;
+        Ok(keymap)
+    }
+
+    fn check_conflicts(&self) -> Result<(), String> {
+        let mut conflicts = vec![];
+
+        for scope in [Scope::Review, Scope::Browse, Scope::Cram, Scope::Menu] {
+            let mut used: HashMap<Key, &str> = HashMap::new();
+
+            for info in ACTIONS.iter().filter(|info| info.scopes.contains(&scope)) {
+                for key in &self.bindings[&info.action] {
+                    if let Some(other) = used.insert(*key, info.name) {
+                        conflicts.push(format!(
+                            "'{}' is bound to both {} and {} in {}",
+                            key, other, info.name, scope
+                        ));
+                    }
+                }
+            }
+        }
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "conflicting keybindings:\n{}",
+                conflicts.join("\n")
+            ))
+        }
+    }
+
+    /// The keys and what they do on the given page, in the order of the action table.
+    pub fn help(&self, scope: Scope) -> Vec<(String, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter(|info| info.scopes.contains(&scope))
+            .map(|info| (self.keys(info.action), info.description))
+            .collect()
+    }
+
+    /// The keys bound to the action, for showing them to the user.
+    pub fn keys(&self, action: Action) -> String {
+        let keys: Vec<String> = self.bindings[&action].iter().map(Key::to_string).collect();
+        keys.join(", ")
+    }
+
+    /// The action the key triggers on the given page.
+    pub fn action(&self, scope: Scope, event: &KeyEvent) -> Option<Action> {
+        let key = Key::from(event);
+        ACTIONS
+            .iter()
+            .filter(|info| info.scopes.contains(&scope))
+            .find(|info| self.bindings[&info.action].contains(&key))
+            .map(|info| info.action)
+    }
+}
+
+static KEYMAP: OnceLock<KeyMap> = OnceLock::new();
+
+/// Must be called at startup, before the first call to [`keymap`].
+pub fn set_keymap(keymap: KeyMap) {
+    assert!(
+        KEYMAP.set(keymap).is_ok(),
+        "the keymap was read before it was set"
+    );
+}
+
+pub fn keymap() -> &'static KeyMap {
+    KEYMAP.get_or_init(|| KeyMap::new(&HashMap::new()).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Result<Key, String> {
+        Ok(Key { code, modifiers })
+    }
+
+    #[test]
+    fn parses_chars_and_named_keys() {
+        assert_eq!("a".parse(), key(KeyCode::Char('a'), KeyModifiers::NONE));
+        assert_eq!("G".parse(), key(KeyCode::Char('G'), KeyModifiers::NONE));
+        assert_eq!("space".parse(), key(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!("Esc".parse(), key(KeyCode::Esc, KeyModifiers::NONE));
+        assert_eq!(
+            "pagedown".parse(),
+            key(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!("f5".parse(), key(KeyCode::F(5), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(
+            "ctrl-z".parse(),
+            key(KeyCode::Char('z'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            "ctrl-alt-enter".parse(),
+            key(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT)
+        );
+    }
+
+    #[test]
+    fn a_dash_on_its_own_is_a_key() {
+        assert_eq!("-".parse(), key(KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!(
+            "ctrl--".parse(),
+            key(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert!("".parse::<Key>().is_err());
+        assert!("foo".parse::<Key>().is_err());
+        assert!("ctrl-".parse::<Key>().is_err());
+        assert!("hyper-a".parse::<Key>().is_err());
+        assert!("fx".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn displays_as_it_parses() {
+        for text in ["a", "space", "ctrl-z", "alt-left", "f12", "pageup", "-"] {
+            let key: Key = text.parse().unwrap();
+            assert_eq!(key.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn the_defaults_dont_conflict() {
+        assert!(KeyMap::new(&HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn overrides_are_checked_for_conflicts() {
+        let overrides = HashMap::from([("list_down".to_string(), Keys::One("q".to_string()))]);
+        assert!(KeyMap::new(&overrides).is_err());
+
+        let overrides = HashMap::from([("reveal".to_string(), Keys::One("1".to_string()))]);
+        assert!(KeyMap::new(&overrides).is_err());
+
+        let overrides = HashMap::from([("no_such_action".to_string(), Keys::One("x".to_string()))]);
+        assert!(KeyMap::new(&overrides).is_err());
+    }
+}
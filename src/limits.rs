@@ -0,0 +1,253 @@
+//! Daily caps on reviews and new cards, set in the `[limits]` section of the config file.
+//!
+//! What's been reviewed today is counted per category in `daily_counts.toml`, and the counts
+//! start over at local midnight.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{Datelike, Local};
+use serde::{Deserialize, Serialize};
+use speki_backend::card::CardCache;
+use speki_backend::categories::Category;
+use speki_backend::paths::get_share_path;
+use speki_backend::Id;
+
+use crate::config::config;
+
+#[derive(Clone, Copy)]
+pub enum Quota {
+    Reviews,
+    New,
+}
+
+impl Quota {
+    fn pick(self, reviews: Option<usize>, new: Option<usize>) -> Option<usize> {
+        match self {
+            Quota::Reviews => reviews,
+            Quota::New => new,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct DailyCounts {
+    day: i32,
+    /// Full category path to how many cards from it were reviewed.
+    reviews: HashMap<String, usize>,
+    new: HashMap<String, usize>,
+}
+
+/// The local calendar day, as days since the start of the common era.
+fn today() -> i32 {
+    Local::now().date_naive().num_days_from_ce()
+}
+
+fn path() -> PathBuf {
+    get_share_path().join("daily_counts.toml")
+}
+
+/// Whether `category` is the configured category `name` or one under it.
+fn is_under(name: &str, category: &str) -> bool {
+    let name = name.trim_matches('/').to_lowercase();
+    let category = category.trim_matches('/').to_lowercase();
+    category == name || category.starts_with(&format!("{}/", name))
+}
+
+impl DailyCounts {
+    fn load() -> Self {
+        let counts: Self = std::fs::read_to_string(path())
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default();
+        counts.on(today())
+    }
+
+    /// The counts as of `day`, nothing used if they're from another day.
+    fn on(self, day: i32) -> Self {
+        if self.day == day {
+            self
+        } else {
+            Self {
+                day,
+                ..Default::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        std::fs::write(path(), toml::to_string(self).unwrap()).unwrap();
+    }
+
+    fn counts(&mut self, quota: Quota) -> &mut HashMap<String, usize> {
+        match quota {
+            Quota::Reviews => &mut self.reviews,
+            Quota::New => &mut self.new,
+        }
+    }
+
+    /// The smallest of the caps that apply to the category, minus what's been used of them.
+    fn remaining(&mut self, quota: Quota, category: &str) -> Option<usize> {
+        let limits = &config().limits;
+        let used = self.counts(quota);
+
+        let global = quota
+            .pick(limits.reviews, limits.new)
+            .map(|cap| cap.saturating_sub(used.values().sum()));
+
+        let per_category = limits
+            .categories
+            .iter()
+            .filter(|(name, _)| is_under(name, category))
+            .filter_map(|(name, limit)| {
+                let cap = quota.pick(limit.reviews, limit.new)?;
+                let used: usize = used
+                    .iter()
+                    .filter(|(counted, _)| is_under(name, counted))
+                    .map(|(_, qty)| qty)
+                    .sum();
+                Some(cap.saturating_sub(used))
+            });
+
+        global.into_iter().chain(per_category).min()
+    }
+
+    /// Counts a card from the category if there's room left for it.
+    fn take(&mut self, quota: Quota, category: String) -> bool {
+        if self.remaining(quota, &category) == Some(0) {
+            return false;
+        }
+        *self.counts(quota).entry(category).or_default() += 1;
+        true
+    }
+}
+
+/// Counts a review towards today's caps.
+pub fn record_review(category: &Category, new: bool) {
+    let quota = if new { Quota::New } else { Quota::Reviews };
+    let mut counts = DailyCounts::load();
+    *counts
+        .counts(quota)
+        .entry(category.print_full())
+        .or_default() += 1;
+    counts.save();
+}
+
+/// Takes back a review that was undone.
+pub fn forget_review(category: &Category, new: bool) {
+    let quota = if new { Quota::New } else { Quota::Reviews };
+    let mut counts = DailyCounts::load();
+    if let Some(qty) = counts.counts(quota).get_mut(&category.print_full()) {
+        *qty = qty.saturating_sub(1);
+    }
+    counts.save();
+}
+
+/// How many more cards from the category can be reviewed today, `None` if nothing caps it.
+pub fn remaining(quota: Quota, category: &Category) -> Option<usize> {
+    DailyCounts::load().remaining(quota, &category.print_full())
+}
+
+/// Keeps the cards, in order, that fit in what's left of today's caps.
+pub fn apply(quota: Quota, cards: Vec<Id>, cache: &mut CardCache) -> Vec<Id> {
+    let mut counts = DailyCounts::load();
+    let mut kept = vec![];
+
+    for card in cards {
+        let category = cache.get_ref(&card).category().print_full();
+        if counts.take(quota, category) {
+            kept.push(card);
+        }
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{set_config, Limit, Limits, TuiConfig};
+
+    fn set_limits(limits: Limits) {
+        set_config(TuiConfig {
+            limits,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn matches_the_category_and_the_ones_under_it() {
+        assert!(is_under("languages", "languages"));
+        assert!(is_under("/Languages/", "languages/spanish/"));
+        assert!(!is_under("languages", "languagesextra"));
+        assert!(!is_under("languages/spanish", "languages"));
+    }
+
+    #[test]
+    fn nothing_caps_without_limits() {
+        set_limits(Limits::default());
+        let mut counts = DailyCounts::default();
+        for _ in 0..100 {
+            assert!(counts.take(Quota::Reviews, "math".into()));
+        }
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), None);
+    }
+
+    #[test]
+    fn stops_at_the_global_limit() {
+        set_limits(Limits {
+            reviews: Some(2),
+            new: Some(0),
+            ..Default::default()
+        });
+        let mut counts = DailyCounts::default();
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), Some(2));
+        assert!(counts.take(Quota::Reviews, "math".into()));
+        assert!(counts.take(Quota::Reviews, "history".into()));
+        assert!(!counts.take(Quota::Reviews, "math".into()));
+        assert_eq!(counts.remaining(Quota::Reviews, "history"), Some(0));
+
+        // A limit of 0 lets nothing through.
+        assert_eq!(counts.remaining(Quota::New, "math"), Some(0));
+        assert!(!counts.take(Quota::New, "math".into()));
+    }
+
+    #[test]
+    fn category_limits_count_their_subcategories() {
+        set_limits(Limits {
+            reviews: Some(10),
+            categories: HashMap::from([(
+                "languages".to_string(),
+                Limit {
+                    reviews: Some(2),
+                    new: None,
+                },
+            )]),
+            ..Default::default()
+        });
+        let mut counts = DailyCounts::default();
+        assert!(counts.take(Quota::Reviews, "languages/spanish".into()));
+        assert!(counts.take(Quota::Reviews, "languages".into()));
+        assert!(!counts.take(Quota::Reviews, "languages/french".into()));
+        // Other categories only have the global limit.
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), Some(8));
+        assert_eq!(counts.remaining(Quota::New, "languages"), None);
+    }
+
+    #[test]
+    fn starts_over_the_next_day() {
+        set_limits(Limits {
+            reviews: Some(1),
+            ..Default::default()
+        });
+        let mut counts = DailyCounts::default().on(today());
+        assert!(counts.take(Quota::Reviews, "math".into()));
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), Some(0));
+
+        let mut counts = counts.on(today());
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), Some(0));
+        let mut counts = counts.on(today() + 1);
+        assert_eq!(counts.remaining(Quota::Reviews, "math"), Some(1));
+    }
+}
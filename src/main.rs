@@ -4,25 +4,39 @@ use crate::pages::health_check;
 use crate::pages::pick_item;
 use crate::pages::print_cool_graphs;
 use crate::pages::print_stats;
-use crate::pages::viewcards::view_all_cards;
-use crate::pages::viewcards::view_cards;
-use std::io::stdout;
+use crate::pages::viewcards::ViewCardsPage;
+use std::io::{stdout, Stdout};
+use std::path::Path;
+use std::time::Duration;
 
-use crate::pages::reviewcards::review_cards;
+use crate::pages::cram::CramPage;
+use crate::pages::reviewcards::ReviewPage;
 
-use backend::{get_following_unfinished_cards, import_stuff};
+use app::{App, Page, PageAction};
+use backend::{get_following_unfinished_cards, import_stuff, save_cards};
+use clap::Parser;
+use cli::Cli;
+use config::{set_config, TuiConfig};
+use keymap::{keymap, set_keymap, Action, KeyMap, Scope};
 use pages::addcards::add_the_cards;
-use pages::{choose_folder, draw_menu, draw_message};
+use pages::help::HelpPage;
+use pages::listview::{ListAction, ListView};
+use pages::{
+    choose_folder, clear_window, draw_menu, draw_warning, edit_in_editor, move_upper_left,
+    read_user_input, undo_last, write_string,
+};
+use queue::Order;
 use speki_backend::card::{CardCache, SavedCard};
 use speki_backend::categories::Category;
+use speki_backend::common::randvec;
 use speki_backend::common::view_cards_in_explorer;
-use speki_backend::common::{open_file_with_vim, randvec};
 use speki_backend::config::Config;
-use speki_backend::git::git_save;
 
 use speki_backend::paths::get_share_path;
+use speki_backend::Id;
 
 use crossterm::cursor::Show;
+use crossterm::event::KeyEvent;
 
 use crossterm::{
     cursor::Hide,
@@ -30,148 +44,185 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
 };
 
+mod app;
 mod backend;
+mod cli;
+mod cloze;
+mod config;
+mod diff;
+mod fuzzy;
+mod keymap;
+mod limits;
 mod pages;
+mod queue;
+mod render;
+mod reverse;
+mod session;
+mod stats;
+mod theme;
+mod undo;
 
-trait Page {}
+const MENU_ITEMS: [&str; 12] = [
+    "Add new cards",
+    "Review cards",
+    "View cards",
+    "Settings",
+    "Debug",
+    "by tag",
+    "notes",
+    "pretty graph",
+    "lonely cards",
+    "health check",
+    "stats",
+    "filters",
+];
 
-struct App {
-    pages: Vec<Box<dyn Page>>,
+fn save_in_background() {
+    let _ = std::thread::spawn(save_cards);
 }
 
-#[tokio::main]
-async fn main() {
-    let mut cache = CardCache::new();
-    import_stuff(&mut cache);
+/// Opens the file in `$EDITOR`, like cards are, and saves it unless it was cancelled.
+fn edit_file(stdout: &mut Stdout, path: &Path) {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    let Some(text) = edit_in_editor(stdout, text, None) else {
+        return;
+    };
+    if let Err(e) = std::fs::write(path, text) {
+        draw_warning(stdout, &format!("couldn't save {}: {}", path.display(), e));
+    }
+}
 
-    enable_raw_mode().unwrap();
-    let mut stdout = stdout();
-    execute!(stdout, Hide).unwrap();
+/// Asks for a folder or tag and opens a cram page over its finished cards.
+fn cram(stdout: &mut Stdout) -> Option<Box<dyn Page>> {
+    let by = draw_menu(stdout, Some("Cram by..."), vec!["Folder", "Tag"], true)?;
+    let cards = SavedCard::load_all_cards()
+        .into_iter()
+        .filter(|card| card.is_finished() && !card.is_suspended());
 
-    /*
-    two_review_stuff();
-    three_review_stuff();
-    fourplus_review_stuff();
-    five_review_stuff();
-    six_review_stuff();
-    */
+    let cards: Vec<Id> = match by {
+        0 => {
+            let categories = choose_folder(stdout, "Folder to cram")?.get_following_categories();
+            cards
+                .filter(|card| categories.contains(card.category()))
+                .map(|card| *card.id())
+                .collect()
+        }
+        _ => {
+            let tags: Vec<String> = Category::get_all_tags().into_iter().collect();
+            let tag = pick_item(stdout, "Tag to cram", &tags)?;
+            cards
+                .filter(|card| card.contains_tag(tag))
+                .map(|card| *card.id())
+                .collect()
+        }
+    };
 
-    let menu_items = vec![
-        "Add new cards",
-        "Review cards",
-        "View cards",
-        "Settings",
-        "Debug",
-        "by tag",
-        "notes",
-        "pretty graph",
-        "lonely cards",
-        "health check",
-        "stats",
-        "filters",
-    ];
-
-    while let Some(choice) = draw_menu(&mut stdout, None, menu_items.clone(), true) {
+    Some(Box::new(CramPage::new(cards)))
+}
+
+#[derive(Default)]
+struct MainMenu {
+    list: ListView,
+    /// Save the collection once we're back from the page we opened.
+    save_on_resume: bool,
+}
+
+impl MainMenu {
+    fn choose(&mut self, choice: usize, stdout: &mut Stdout, cache: &mut CardCache) -> PageAction {
         match choice {
             0 => {
-                let Some(category) = choose_folder(&mut stdout, "Folder to add card to") else {
-                    continue;
+                let Some(category) = choose_folder(stdout, "Folder to add card to") else {
+                    return PageAction::None;
                 };
-                add_the_cards(&mut stdout, category, &mut cache);
-                let has_remote = Config::load().unwrap().git_remote.is_some();
-                let _ = std::thread::spawn(move || git_save(has_remote));
+                add_the_cards(stdout, category, cache);
+                save_in_background();
             }
             1 => {
                 let Some(revtype) = draw_menu(
-                    &mut stdout,
+                    stdout,
                     None,
-                    vec!["Normal", "Pending", "Unfinished", "Random review"],
+                    vec![
+                        "Normal",
+                        "Pending",
+                        "Unfinished",
+                        "Cram",
+                        "Timeboxed",
+                        "Interleaved",
+                        "Dependencies first",
+                    ],
                     true,
                 ) else {
-                    continue;
+                    return PageAction::None;
                 };
 
-                let Some(category) = choose_folder(&mut stdout, "Choose review type") else {
-                    continue;
+                if revtype == 3 {
+                    let Some(page) = cram(stdout) else {
+                        return PageAction::None;
+                    };
+                    return PageAction::Push(page);
+                }
+
+                let Some(category) = choose_folder(stdout, "Choose review type") else {
+                    return PageAction::None;
                 };
 
-                match revtype {
-                    0 => {
-                        review_cards(
-                            &mut stdout,
-                            category.clone(),
-                            Box::new(Category::get_review_cards),
-                            &mut cache,
-                            true,
-                            false,
-                        );
-                        draw_message(&mut stdout, "now reviewing pending cards");
-                        review_cards(
-                            &mut stdout,
-                            category.clone(),
-                            Box::new(Category::get_pending_cards),
-                            &mut cache,
-                            true,
-                            false,
-                        );
-                    }
-                    1 => {
-                        review_cards(
-                            &mut stdout,
-                            category.clone(),
-                            Box::new(Category::get_pending_cards),
-                            &mut cache,
-                            true,
-                            false,
-                        );
-                    }
+                let page: Box<dyn Page> = match revtype {
+                    0 => Box::new(ReviewPage::normal(category)),
+                    1 => Box::new(ReviewPage::new(
+                        category,
+                        Box::new(Category::get_pending_cards),
+                        true,
+                        Order::Gain,
+                    )),
                     2 => {
-                        let mut cards = get_following_unfinished_cards(&category, &mut cache);
+                        let mut cards = get_following_unfinished_cards(&category, cache);
                         cards.sort_by_key(|card| {
-                            cache.get_ref(card).get_unfinished_dependent_qty(&mut cache)
+                            cache.get_ref(card).get_unfinished_dependent_qty(cache)
                         });
                         cards.reverse();
-                        view_cards(&mut stdout, cards, &mut cache);
+                        Box::new(ViewCardsPage::new(cards))
                     }
-                    3 => {
-                        review_cards(
-                            &mut stdout,
-                            category.clone(),
-                            Box::new(Category::get_random_review_cards),
-                            &mut cache,
-                            false,
-                            true,
-                        );
+                    4 => {
+                        clear_window(stdout);
+                        move_upper_left(stdout);
+                        write_string(stdout, "Minutes to review for: ");
+                        let Some(minutes) = read_user_input(stdout)
+                            .and_then(|(input, _)| input.trim().parse::<u64>().ok())
+                        else {
+                            return PageAction::None;
+                        };
+                        Box::new(ReviewPage::timeboxed(
+                            category,
+                            Duration::from_secs(minutes * 60),
+                        ))
                     }
-                    _ => continue,
-                }
+                    5 => Box::new(ReviewPage::ordered(category, Order::Interleaved)),
+                    6 => Box::new(ReviewPage::ordered(category, Order::Dependencies)),
+                    _ => return PageAction::None,
+                };
 
-                let has_remote = Config::load().unwrap().git_remote.is_some();
-                let _ = std::thread::spawn(move || git_save(has_remote));
+                self.save_on_resume = true;
+                return PageAction::Push(page);
             }
             2 => view_cards_in_explorer(),
             3 => {
                 let _ = Config::edit_with_vim();
             }
-            4 => {
-                view_all_cards(&mut stdout, &mut cache);
-            }
+            4 => return PageAction::Push(Box::new(ViewCardsPage::all_cards(cache))),
             5 => {
                 let tags: Vec<String> = Category::get_all_tags().into_iter().collect();
-                let tag = pick_item(&mut stdout, "Tag to filter by", &tags);
+                let tag = pick_item(stdout, "Tag to filter by", &tags);
                 if let Some(tag) = tag {
                     let cards = SavedCard::load_all_cards()
                         .into_iter()
                         .filter_map(|card| card.contains_tag(tag).then(|| card.id().to_owned()))
                         .collect();
-                    view_cards(&mut stdout, cards, &mut cache);
+                    return PageAction::Push(Box::new(ViewCardsPage::new(cards)));
                 }
             }
-            6 => {
-                open_file_with_vim(get_share_path().join("notes").as_path()).unwrap();
-            }
+            6 => edit_file(stdout, &get_share_path().join("notes")),
             7 => {
-                print_cool_graphs(&mut stdout, &mut cache);
+                print_cool_graphs(stdout, cache);
             }
             8 => {
                 let mut cards = SavedCard::load_all_cards()
@@ -184,16 +235,108 @@ async fn main() {
                 });
                 let cards = randvec(cards);
                 let cards = cards.into_iter().map(|card| card.id().to_owned()).collect();
-                view_cards(&mut stdout, cards, &mut cache);
+                return PageAction::Push(Box::new(ViewCardsPage::new(cards)));
             }
             9 => {
-                health_check(&mut stdout, &mut cache);
+                health_check(stdout, cache);
             }
-            10 => print_stats(&mut stdout, &mut cache),
+            10 => print_stats(stdout, cache),
             _ => {}
         };
+        PageAction::None
+    }
+}
+
+impl Page for MainMenu {
+    fn render(&mut self, stdout: &mut Stdout, _cache: &mut CardCache) {
+        let items: Vec<String> = MENU_ITEMS.iter().map(|item| item.to_string()).collect();
+        clear_window(stdout);
+        move_upper_left(stdout);
+        self.list.render(stdout, 1, &items);
     }
+
+    fn handle_key(
+        &mut self,
+        stdout: &mut Stdout,
+        key: KeyEvent,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        match self.list.handle_key(&key) {
+            ListAction::Select(choice) => {
+                execute!(stdout, Clear(ClearType::All)).unwrap();
+                self.choose(choice, stdout, cache)
+            }
+            ListAction::Ignored => match keymap().action(Scope::Menu, &key) {
+                Some(Action::Help) => PageAction::Push(Box::new(HelpPage::new(Scope::Menu))),
+                Some(Action::Quit) => PageAction::Pop,
+                Some(Action::Undo) => {
+                    undo_last(stdout, cache);
+                    PageAction::None
+                }
+                _ => PageAction::None,
+            },
+            ListAction::Moved => PageAction::None,
+        }
+    }
+
+    fn on_resume(&mut self, _stdout: &mut Stdout, _cache: &mut CardCache) {
+        if std::mem::take(&mut self.save_on_resume) {
+            save_in_background();
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    // Before any command runs, since the pages they build read the config.
+    let config = TuiConfig::load()
+        .and_then(|config| KeyMap::new(&config.keys).map(|keymap| (config, keymap)));
+    match config {
+        Ok((config, keymap)) => {
+            set_config(config);
+            set_keymap(keymap);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let mut cache = CardCache::new();
+
+    // Commands that need the ui hand us the page to start on, the rest are already done.
+    let (root, save_on_exit): (Box<dyn Page>, bool) = match cli.command {
+        Some(command) => match cli::run(command, &mut cache) {
+            Some(page) => (page, true),
+            None => return,
+        },
+        None => {
+            import_stuff(&mut cache);
+            (Box::<MainMenu>::default(), false)
+        }
+    };
+
+    enable_raw_mode().unwrap();
+    let mut stdout = stdout();
+    execute!(stdout, Hide).unwrap();
+
+    /*
+    two_review_stuff();
+    three_review_stuff();
+    fourplus_review_stuff();
+    five_review_stuff();
+    six_review_stuff();
+    */
+
+    App::new(root).run(&mut stdout, &mut cache);
+
     execute!(stdout, Clear(ClearType::All)).unwrap();
     execute!(stdout, Show).unwrap();
     disable_raw_mode().unwrap();
+
+    if save_on_exit {
+        save_cards();
+    }
 }
@@ -1,19 +1,30 @@
 use std::io::Stdout;
 
+use crossterm::cursor::{Hide, Show};
+use crossterm::execute;
+
 use speki_backend::card::{Card, CardCache, SavedCard};
 use speki_backend::categories::Category;
 
 use speki_backend::Id;
 
-use crate::backend::get_text_from_vim;
+use crate::backend::{get_input, Input};
+use crate::cloze;
+use crate::config::config;
+use crate::reverse;
+use crate::undo::Change;
 
-use super::draw_message;
+use super::editor::{Editor, EditorAction};
+use super::{draw_message, draw_warning, edit_in_editor};
 
+/// Returns the cards that were made, one per cloze for a note with clozes and none if the card
+/// was cancelled.
 pub fn add_card(
+    stdout: &mut Stdout,
     initial_text: Option<(String, String)>,
     category: &mut Category,
     cache: &mut CardCache,
-) -> Option<SavedCard> {
+) -> Vec<SavedCard> {
     let vimrc = r#"
 function! AddTextAtBeginning()
     " Move the cursor to the beginning of the file
@@ -28,18 +39,30 @@ function! AddTextAtBeginning()
     quit
 endfunction
 
+function! AddReverseMarker()
+    call setline(1, '<reverse>' . getline(1))
+endfunction
+
 function! SetSigns()
     set signcolumn=yes
     sign unplace *
     execute "sign define QSign text=Q: "
     execute "sign define ASign text=A: "
-    execute "sign place 1 line=1 name=QSign"
-    execute "sign place 2 line=2 name=ASign"
+    " The front starts after the header lines and the back after the delimiter.
+    let front = 1
+    while front < line('$') && getline(front) =~# '^\(tags\|priority\):'
+        let front += 1
+    endwhile
+    execute "sign place 1 line=" . front . " name=QSign"
+    let delimiter = index(getline(1, '$'), '<!-- back -->') + 1
+    if delimiter > 0 && delimiter < line('$')
+        execute "sign place 2 line=" . (delimiter + 1) . " name=ASign"
+    endif
 endfunction
 
 highlight SignColumn guibg=none ctermbg=none
 
-autocmd VimEnter * normal 50o
+autocmd VimEnter * normal G50o
 autocmd VimEnter * normal gg
 autocmd VimEnter * call SetSigns()
 autocmd BufWritePost,TextChanged,TextChangedI * call SetSigns()
@@ -55,69 +78,465 @@ inoremap <C-q> <Esc>:q!<CR>
 
 inoremap <C-u> <Esc>:call AddTextAtBeginning()wq<CR>
 nnoremap <C-u> :call AddTextAtBeginning()<CR>
+
+inoremap <C-b> <Esc>:call AddReverseMarker()<CR>a
+nnoremap <C-b> :call AddReverseMarker()<CR>
 "#;
 
-    let initial_text = initial_text.map(|(mut front, back)| {
-        front.push('\n');
-        front.push_str(&back);
-        front
-    });
+    let initial_text = match initial_text {
+        Some((front, back)) => joined(&front, &back),
+        None => joined("", ""),
+    };
+
+    let text = if config().editor.external {
+        edit_in_editor(stdout, initial_text, Some(vimrc))
+    } else {
+        write_card(stdout, &initial_text)
+    };
+    let Some(text) = text else {
+        return vec![];
+    };
+    let template = Template::parse(&text);
+    if template.front.is_empty() {
+        return vec![];
+    }
+
+    let mut cards: Vec<SavedCard> = vec![];
+    for new in template.cards() {
+        let mut card = Card::new_simple(new.front, new.back);
+        card.meta.finished = new.finished;
+        let mut card = card.save_new_card(category, cache);
+        for tag in new.tags {
+            card.insert_tag(tag);
+        }
+        if let Some(priority) = new.priority {
+            card.set_priority(priority.into());
+        }
+        if let Some(original) = cards.last().filter(|_| new.reverse) {
+            reverse::link(original.id(), card.id());
+        }
+        cards.push(cache.get_owned(card.id()));
+    }
+
+    if let Some(warning) = &template.warning {
+        draw_warning(stdout, warning);
+    }
+
+    cards
+}
+
+/// Writes the card in the built-in editor, with the same markers as the vim bindings.
+fn write_card(stdout: &mut Stdout, initial_text: &str) -> Option<String> {
+    let help = "ctrl-f save   ctrl-u save as unfinished   ctrl-b add reverse   esc cancel";
+    let mut editor = Editor::new(initial_text);
+
+    execute!(stdout, Show).unwrap();
+    let text = loop {
+        editor.render(stdout, 0, &signs(&editor.lines(), true), help);
+        let key = match get_input() {
+            Input::Key(key) => key,
+            Input::Resize => continue,
+        };
+
+        match editor.handle_key(&key) {
+            EditorAction::Save => break Some(editor.text()),
+            EditorAction::Cancel => break None,
+            EditorAction::Ctrl('u') => break Some(format!("<unfinished>{}", editor.text())),
+            EditorAction::Ctrl('b') if !editor.text().starts_with("<reverse>") => {
+                editor.prepend("<reverse>")
+            }
+            _ => {}
+        }
+    };
+    execute!(stdout, Hide).unwrap();
+    text
+}
+
+/// Edits the front and back of a card, in `$EDITOR` or the built-in editor depending on the
+/// config. `None` if it was cancelled.
+///
+/// Unlike a new card there are no header lines, so a front starting with `tags:` stays as it is.
+pub fn edit_card_text(
+    stdout: &mut Stdout,
+    card: &SavedCard,
+    cache: &mut CardCache,
+) -> Option<SavedCard> {
+    let text = joined(card.front_text(), card.back_text());
+    let text = if config().editor.external {
+        edit_in_editor(stdout, text, None)?
+    } else {
+        rewrite_card(stdout, &text)?
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let (front, back) = split(&lines);
+
+    let mut edited = cache.get_owned(card.id());
+    if front != edited.front_text() {
+        edited.set_front_text(&front);
+    }
+    if back != edited.back_text() {
+        edited.set_back_text(&back);
+    }
+    cache.refresh();
+    Some(cache.get_owned(card.id()))
+}
+
+fn rewrite_card(stdout: &mut Stdout, text: &str) -> Option<String> {
+    let help = "ctrl-f save   esc cancel";
+    let mut editor = Editor::new(text);
 
-    let text = get_text_from_vim(initial_text, Some(vimrc)).ok()??;
+    execute!(stdout, Show).unwrap();
+    let text = loop {
+        editor.render(stdout, 0, &signs(&editor.lines(), false), help);
+        let key = match get_input() {
+            Input::Key(key) => key,
+            Input::Resize => continue,
+        };
 
-    let (front, back) = match text.split_once('\n') {
-        Some((front, back)) => (front.trim().to_string(), back.trim().to_string()),
-        None => (text, String::default()),
+        match editor.handle_key(&key) {
+            EditorAction::Save => break Some(editor.text()),
+            EditorAction::Cancel => break None,
+            _ => {}
+        }
     };
+    execute!(stdout, Hide).unwrap();
+    text
+}
+
+/// Where the front and back start, like the signs of the vimrc. The front comes after the header
+/// lines when there can be `headers`.
+fn signs(lines: &[String], headers: bool) -> Vec<(usize, &'static str)> {
+    let front = lines
+        .iter()
+        .position(|line| !headers || !line.starts_with("tags:") && !line.starts_with("priority:"))
+        .unwrap_or_default();
+    let mut signs = vec![(front, "Q:")];
+    if let Some(delimiter) = lines.iter().position(|line| line.trim() == DELIMITER) {
+        signs.push((delimiter + 1, "A:"));
+    }
+    signs
+}
+
+/// Separates the front from the back in the editor. A comment rather than something like `---`,
+/// which is a rule in the markdown of the cards.
+const DELIMITER: &str = "<!-- back -->";
+
+/// A card as written in the editor.
+///
+/// ```text
+/// tags: rust, ownership
+/// priority: 80
+/// the front, on as many lines as it needs
+/// <!-- back -->
+/// the back
+/// ```
+///
+/// The header lines are optional. Without a delimiter the first line is the front and the rest the back.
+struct Template {
+    front: String,
+    back: String,
+    tags: Vec<String>,
+    /// From 0 to 100.
+    priority: Option<u32>,
+    finished: bool,
+    reverse: bool,
+    /// What was wrong with the headers, to show once the card is saved.
+    warning: Option<String>,
+}
+
+/// A card a template makes, with the template's headers.
+#[derive(Debug, PartialEq)]
+struct NewCard {
+    front: String,
+    back: String,
+    tags: Vec<String>,
+    priority: Option<u32>,
+    finished: bool,
+    /// It's the reverse of the card before it.
+    reverse: bool,
+}
+
+impl Template {
+    /// One card per cloze for a note with clozes. Otherwise the card, followed by its reverse if
+    /// the template asks for one and the card [can have it](reverse::can_reverse).
+    fn cards(&self) -> Vec<NewCard> {
+        let mut sides = cloze::note_cards(&self.front, &self.back);
+        let reverse = sides.is_empty() && self.reverse && reverse::can_reverse(&self.back);
+        if sides.is_empty() {
+            sides.push((self.front.clone(), self.back.clone()));
+        }
+        if reverse {
+            sides.push((self.back.clone(), self.front.clone()));
+        }
+
+        sides
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (front, back))| NewCard {
+                front,
+                back,
+                tags: self.tags.clone(),
+                priority: self.priority,
+                finished: self.finished,
+                reverse: reverse && idx == 1,
+            })
+            .collect()
+    }
+}
+
+/// The front and back as they're written in the editor, the reverse of [`split`].
+fn joined(front: &str, back: &str) -> String {
+    format!("{}\n{}\n{}", front, DELIMITER, back)
+}
 
-    let (front, is_finished) = match front.strip_prefix("<unfinished>") {
-        Some(rest) => (rest.to_string(), false),
-        None => (front, true),
+/// The front and back of the lines, split at the delimiter.
+fn split(lines: &[&str]) -> (String, String) {
+    let (front, back) = match lines.iter().position(|line| line.trim() == DELIMITER) {
+        Some(idx) => (&lines[..idx], &lines[idx + 1..]),
+        None => lines.split_at(lines.len().min(1)),
     };
+    (
+        front.join("\n").trim().to_string(),
+        back.join("\n").trim().to_string(),
+    )
+}
 
-    let mut card = Card::new_simple(front, back);
-    card.meta.finished = is_finished;
+/// A priority from 0 to 100, clamped when it's outside that, or why it couldn't be read.
+fn parse_priority(text: &str) -> (Option<u32>, Option<String>) {
+    match text.trim().parse::<i64>() {
+        Ok(priority) if (0..=100).contains(&priority) => (Some(priority as u32), None),
+        Ok(priority) => {
+            let clamped = priority.clamp(0, 100) as u32;
+            let warning = format!(
+                "priority {} is outside 0-100, {} was used",
+                priority, clamped
+            );
+            (Some(clamped), Some(warning))
+        }
+        Err(_) => {
+            let warning = format!(
+                "priority \"{}\" isn't a number from 0 to 100, it was left out",
+                text.trim()
+            );
+            (None, Some(warning))
+        }
+    }
+}
+
+impl Template {
+    fn parse(text: &str) -> Self {
+        let mut template = Self {
+            front: String::new(),
+            back: String::new(),
+            tags: vec![],
+            priority: None,
+            finished: true,
+            reverse: false,
+            warning: None,
+        };
+
+        // The markers the vim bindings put at the very start, in whichever order they were added.
+        let mut text = text;
+        loop {
+            if let Some(rest) = text.strip_prefix("<unfinished>") {
+                template.finished = false;
+                text = rest;
+            } else if let Some(rest) = text.strip_prefix("<reverse>") {
+                template.reverse = true;
+                text = rest;
+            } else {
+                break;
+            }
+        }
 
-    Some(card.save_new_card(category, cache))
+        let mut lines = text.lines().peekable();
+        while let Some(line) = lines.peek() {
+            if let Some(tags) = line.strip_prefix("tags:") {
+                template.tags.extend(
+                    tags.split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from),
+                );
+            } else if let Some(priority) = line.strip_prefix("priority:") {
+                (template.priority, template.warning) = parse_priority(priority);
+            } else {
+                break;
+            }
+            lines.next();
+        }
+
+        let body: Vec<&str> = lines.collect();
+        (template.front, template.back) = split(&body);
+        template
+    }
 }
 
-pub fn add_the_cards(_stdout: &mut Stdout, mut category: Category, cache: &mut CardCache) {
-    while add_card(None, &mut category, cache).is_some() {}
+pub fn add_the_cards(stdout: &mut Stdout, mut category: Category, cache: &mut CardCache) {
+    while !add_card(stdout, None, &mut category, cache).is_empty() {}
 }
 
+/// Every card that was made is linked, so all the cards of a cloze note become dependencies.
 pub fn add_dependency(
     stdout: &mut Stdout,
     card: &Id,
     category: Option<&Category>,
     cache: &mut CardCache,
-) -> Option<SavedCard> {
+) -> Vec<SavedCard> {
     let mut card = cache.get_owned(card);
     let category = category.unwrap_or_else(|| card.category());
     let category = &mut category.to_owned();
-    let new_dependency = add_card(None, category, cache)?;
-    let info = card.set_dependency(new_dependency.id(), cache);
+    let new_dependencies = add_card(stdout, None, category, cache);
+    if new_dependencies.is_empty() {
+        return vec![];
+    }
+
+    let mut change = Change::new("new dependency").card(card.id(), cache);
+    let mut info = vec![];
+    for new_dependency in &new_dependencies {
+        change.created(new_dependency.as_path());
+        info.extend(card.set_dependency(new_dependency.id(), cache));
+    }
+    change.record();
 
-    if let Some(info) = info {
-        draw_message(stdout, &info);
+    if !info.is_empty() {
+        draw_message(stdout, &info.join("\n"));
     }
     cache.refresh();
-    Some(new_dependency)
+    new_dependencies
 }
 
+/// Every card that was made is linked, so all the cards of a cloze note become dependents.
 pub fn add_dependent(
     stdout: &mut Stdout,
     card: &Id,
     category: Option<&Category>,
     cache: &mut CardCache,
-) -> Option<SavedCard> {
+) -> Vec<SavedCard> {
     let mut card = cache.get_owned(card);
     let mut category = category.cloned().unwrap_or_else(|| card.category().clone());
-    let new_dependent = add_card(None, &mut category, cache)?;
-    let info = card.set_dependent(new_dependent.id(), cache);
+    let new_dependents = add_card(stdout, None, &mut category, cache);
+    if new_dependents.is_empty() {
+        return vec![];
+    }
 
-    if let Some(info) = info {
-        draw_message(stdout, &info);
+    let mut change = Change::new("new dependent").card(card.id(), cache);
+    let mut info = vec![];
+    for new_dependent in &new_dependents {
+        change.created(new_dependent.as_path());
+        info.extend(card.set_dependent(new_dependent.id(), cache));
+    }
+    change.record();
+
+    if !info.is_empty() {
+        draw_message(stdout, &info.join("\n"));
     }
     cache.refresh();
-    Some(new_dependent)
+    new_dependents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers_front_and_back() {
+        let template = Template::parse(
+            "tags: rust, ownership,\npriority: 80\nfront\nline two\n<!-- back -->\nback\n",
+        );
+        assert_eq!(template.tags, ["rust", "ownership"]);
+        assert_eq!(template.priority, Some(80));
+        assert_eq!(template.front, "front\nline two");
+        assert_eq!(template.back, "back");
+        assert!(template.finished && !template.reverse);
+        assert!(template.warning.is_none());
+    }
+
+    #[test]
+    fn first_line_is_the_front_without_a_delimiter() {
+        let template = Template::parse("front\nback\nmore back");
+        assert_eq!(template.front, "front");
+        assert_eq!(template.back, "back\nmore back");
+
+        let template = Template::parse("");
+        assert!(template.front.is_empty() && template.back.is_empty());
+    }
+
+    #[test]
+    fn reads_the_markers_in_any_order() {
+        let template = Template::parse("<reverse><unfinished>front\n<!-- back -->\nback");
+        assert!(!template.finished && template.reverse);
+        assert_eq!(template.front, "front");
+    }
+
+    #[test]
+    fn headers_only_count_at_the_top() {
+        let template = Template::parse("front\ntags: not a header\n<!-- back -->\npriority: 3");
+        assert!(template.tags.is_empty());
+        assert_eq!(template.priority, None);
+        assert_eq!(template.front, "front\ntags: not a header");
+        assert_eq!(template.back, "priority: 3");
+    }
+
+    #[test]
+    fn keeps_the_priority_within_bounds() {
+        assert_eq!(parse_priority(" 0 "), (Some(0), None));
+        assert_eq!(parse_priority("100"), (Some(100), None));
+
+        let (priority, warning) = parse_priority("250");
+        assert_eq!(priority, Some(100));
+        assert!(warning.is_some());
+
+        let (priority, warning) = parse_priority("-5");
+        assert_eq!(priority, Some(0));
+        assert!(warning.is_some());
+
+        let (priority, warning) = parse_priority("high");
+        assert_eq!(priority, None);
+        assert!(warning.is_some());
+    }
+
+    #[test]
+    fn edits_split_without_headers() {
+        let lines = ["tags: kept", "front", DELIMITER, "back"];
+        assert_eq!(
+            split(&lines),
+            ("tags: kept\nfront".to_string(), "back".to_string())
+        );
+    }
+
+    #[test]
+    fn the_reverse_card_gets_the_headers_too() {
+        let text = format!("<reverse>tags: geography\npriority: 70\nOslo\n{DELIMITER}\nNorway");
+        let cards = Template::parse(&text).cards();
+        assert_eq!(cards.len(), 2);
+        let reverse = &cards[1];
+        assert!(reverse.reverse && !cards[0].reverse);
+        assert_eq!(
+            (reverse.front.as_str(), reverse.back.as_str()),
+            ("Norway", "Oslo")
+        );
+        assert_eq!(reverse.tags, ["geography"]);
+        assert_eq!(reverse.priority, Some(70));
+    }
+
+    #[test]
+    fn cloze_notes_and_cards_without_a_back_get_no_reverse() {
+        let text = format!("<reverse>{{{{c1::Oslo}}}} is in {{{{c2::Norway}}}}\n{DELIMITER}");
+        let cards = Template::parse(&text).cards();
+        assert_eq!(cards.len(), 2);
+        assert!(cards.iter().all(|card| !card.reverse));
+
+        let cards = Template::parse("<reverse>only a front").cards();
+        assert_eq!(cards.len(), 1);
+    }
+
+    #[test]
+    fn rules_survive_an_edit() {
+        let front = "above the rule\n---\nbelow the rule";
+        let back = "the back\n***\nmore";
+        let text = joined(front, back);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(split(&lines), (front.to_string(), back.to_string()));
+    }
 }
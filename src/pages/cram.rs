@@ -0,0 +1,197 @@
+//! Practice over a set of cards that leaves their review history alone.
+//!
+//! Grades go to `practice.log` instead of the cards, and failed cards come back
+//! later in the session until they're passed.
+
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{Stdout, Write};
+use std::time::Duration;
+
+use crossterm::event::KeyEvent;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use rand::seq::SliceRandom;
+use speki_backend::card::{CardCache, Grade};
+use speki_backend::common::current_time;
+use speki_backend::paths::get_share_path;
+use speki_backend::Id;
+
+use crate::app::{Page, PageAction};
+use crate::keymap::{keymap, Action, Scope};
+use crate::render::layout::Scroll;
+
+use super::help::HelpPage;
+use super::reviewcards::print_card_for_review;
+use super::{card_layout, draw_message, edit_card, scroll_card, undo_last, write_string};
+
+pub struct CramPage {
+    /// Cards that haven't been passed yet, the front one is on screen.
+    queue: VecDeque<Id>,
+    passed: usize,
+    attempts: usize,
+    show_backside: bool,
+    start_time: Duration,
+    duration: Duration,
+    /// How far the card is scrolled.
+    scroll: Scroll,
+}
+
+impl CramPage {
+    pub fn new(mut cards: Vec<Id>) -> Self {
+        cards.shuffle(&mut rand::thread_rng());
+        Self {
+            queue: cards.into(),
+            passed: 0,
+            attempts: 0,
+            show_backside: false,
+            start_time: current_time(),
+            duration: Duration::default(),
+            scroll: Scroll::default(),
+        }
+    }
+
+    fn show_next(&mut self, cache: &mut CardCache) {
+        self.queue.retain(|card| cache.exists(card));
+        self.show_backside = false;
+        self.start_time = current_time();
+        self.duration = Duration::default();
+        self.scroll = Scroll::default();
+    }
+
+    fn finish(&self, stdout: &mut Stdout) -> PageAction {
+        draw_message(
+            stdout,
+            &format!(
+                "Cram done!\n\npassed: {}\nattempts: {}",
+                self.passed, self.attempts
+            ),
+        );
+        PageAction::Pop
+    }
+
+    fn grade(
+        &mut self,
+        card: Id,
+        grade: char,
+        stdout: &mut Stdout,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        let Ok(parsed) = grade.to_string().parse::<Grade>() else {
+            return PageAction::None;
+        };
+
+        log_practice(&card, grade, self.duration);
+        self.attempts += 1;
+        self.queue.pop_front();
+        if matches!(parsed, Grade::None | Grade::Late) {
+            self.queue.push_back(card);
+        } else {
+            self.passed += 1;
+        }
+
+        self.show_next(cache);
+        if self.queue.is_empty() {
+            return self.finish(stdout);
+        }
+        PageAction::None
+    }
+}
+
+/// Adds a line with the grade to `practice.log` in the share folder.
+fn log_practice(card: &Id, grade: char, duration: Duration) {
+    let path = get_share_path().join("practice.log");
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{:.1}s",
+        current_time().as_secs(),
+        card,
+        grade,
+        duration.as_secs_f32()
+    )
+    .unwrap();
+}
+
+impl Page for CramPage {
+    fn render(&mut self, stdout: &mut Stdout, cache: &mut CardCache) {
+        let Some(card) = self.queue.front() else {
+            execute!(stdout, Clear(ClearType::All)).unwrap();
+            write_string(stdout, "Nothing to cram!");
+            return;
+        };
+
+        let card = cache.get_ref(card);
+        let status = format!(
+            "cram\t{} passed, {} left\t{}",
+            self.passed,
+            self.queue.len(),
+            card.category().print_full()
+        );
+        let layout = card_layout(card.card_as_ref(), self.show_backside, vec![], 1);
+        print_card_for_review(stdout, &layout, self.scroll, &status);
+    }
+
+    fn handle_key(
+        &mut self,
+        stdout: &mut Stdout,
+        key: KeyEvent,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        let Some(card_id) = self.queue.front().copied() else {
+            return PageAction::Pop;
+        };
+
+        let Some(action) = keymap().action(Scope::Cram, &key) else {
+            return PageAction::None;
+        };
+
+        if let Some(grade) = action.grade() {
+            return match self.show_backside {
+                true => self.grade(card_id, grade, stdout, cache),
+                false => PageAction::None,
+            };
+        }
+
+        if edit_card(stdout, action, cache.get_ref(&card_id), cache) {
+            self.queue.retain(|card| cache.exists(card));
+            return PageAction::None;
+        }
+
+        let card = cache.get_ref(&card_id);
+        let layout = card_layout(card.card_as_ref(), self.show_backside, vec![], 1);
+        if let Some(scroll) = scroll_card(action, self.scroll, &layout) {
+            self.scroll = scroll;
+            return PageAction::None;
+        }
+
+        match action {
+            Action::Reveal => {
+                if !self.show_backside {
+                    self.duration = current_time() - self.start_time;
+                }
+                self.show_backside = true;
+            }
+            Action::Skip => {
+                self.queue.rotate_left(1);
+                self.show_next(cache);
+            }
+            Action::Undo => {
+                undo_last(stdout, cache);
+                self.queue.retain(|card| cache.exists(card));
+            }
+            Action::Help => return PageAction::Push(Box::new(HelpPage::new(Scope::Cram))),
+            Action::Quit => return self.finish(stdout),
+            _ => {}
+        }
+        PageAction::None
+    }
+
+    fn on_resume(&mut self, _stdout: &mut Stdout, cache: &mut CardCache) {
+        self.queue.retain(|card| cache.exists(card));
+    }
+}
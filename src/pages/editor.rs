@@ -0,0 +1,358 @@
+//! Multi-line text editor for writing cards without leaving the tui.
+//!
+//! Typing inserts at the cursor, the arrows, home, end and the page keys move it,
+//! ctrl-f saves and esc or ctrl-q cancels.
+
+use std::io::{Stdout, Write};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::PrintStyledContent;
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::theme::theme;
+
+pub enum EditorAction {
+    /// The text or the cursor changed.
+    Moved,
+    Save,
+    Cancel,
+    /// Ctrl and a char the editor doesn't use, up to the caller to handle it.
+    Ctrl(char),
+}
+
+/// Width of the column with the signs, left of the text.
+const SIGN_WIDTH: u16 = 3;
+
+pub struct Editor {
+    lines: Vec<Vec<char>>,
+    row: usize,
+    /// Index into the chars of the cursor's line.
+    col: usize,
+    /// Line and column in the top left corner of the screen.
+    offset: (usize, usize),
+    /// Text rows on screen, as of the last render.
+    height: usize,
+}
+
+impl Editor {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<Vec<char>> = text
+            .split('\n')
+            .map(|line| line.chars().collect())
+            .collect();
+        if lines.is_empty() {
+            lines.push(vec![]);
+        }
+        Self {
+            lines,
+            row: 0,
+            col: 0,
+            offset: (0, 0),
+            height: 1,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+
+    /// Puts the text in front of the first line, leaving the cursor where it is.
+    pub fn prepend(&mut self, text: &str) {
+        let len = text.chars().count();
+        self.lines[0].splice(0..0, text.chars());
+        if self.row == 0 {
+            self.col += len;
+        }
+    }
+
+    pub fn move_to(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.col = col.min(self.lines[self.row].len());
+    }
+
+    fn insert(&mut self, c: char) {
+        self.lines[self.row].insert(self.col, c);
+        self.col += 1;
+    }
+
+    fn newline(&mut self) {
+        let rest = self.lines[self.row].split_off(self.col);
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            self.lines[self.row].remove(self.col);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+            self.lines[self.row].extend(line);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.lines[self.row].remove(self.col);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].extend(line);
+        }
+    }
+
+    fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+        }
+    }
+
+    fn right(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> EditorAction {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return match key.code {
+                KeyCode::Char('f') => EditorAction::Save,
+                KeyCode::Char('q') => EditorAction::Cancel,
+                KeyCode::Char(c) => EditorAction::Ctrl(c),
+                _ => EditorAction::Moved,
+            };
+        }
+
+        let page = self.height.max(1);
+        match key.code {
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Tab => (0..4).for_each(|_| self.insert(' ')),
+            KeyCode::Enter => self.newline(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.left(),
+            KeyCode::Right => self.right(),
+            KeyCode::Up => self.move_to(self.row.saturating_sub(1), self.col),
+            KeyCode::Down => self.move_to(self.row + 1, self.col),
+            KeyCode::PageUp => self.move_to(self.row.saturating_sub(page), self.col),
+            KeyCode::PageDown => self.move_to(self.row + page, self.col),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.lines[self.row].len(),
+            KeyCode::Esc => return EditorAction::Cancel,
+            _ => {}
+        }
+
+        EditorAction::Moved
+    }
+
+    /// Keeps the cursor on screen.
+    fn clamp(&mut self, width: usize) {
+        let (top, left) = &mut self.offset;
+        if self.row < *top {
+            *top = self.row;
+        }
+        if self.row >= *top + self.height {
+            *top = self.row + 1 - self.height;
+        }
+        if self.col < *left {
+            *left = self.col;
+        }
+        if self.col >= *left + width {
+            *left = self.col + 1 - width;
+        }
+    }
+
+    /// Draws the text from row `top` down to the bottom of the screen, with `signs` in the
+    /// column left of the lines they're for. The last row shows `help`.
+    pub fn render(&mut self, stdout: &mut Stdout, top: u16, signs: &[(usize, &str)], help: &str) {
+        let (width, rows) = terminal::size().unwrap();
+        let text_width = width.saturating_sub(SIGN_WIDTH).max(1) as usize;
+        self.height = rows.saturating_sub(top + 1).max(1) as usize;
+        self.clamp(text_width);
+
+        execute!(stdout, Clear(ClearType::All)).unwrap();
+        let (first_line, first_col) = self.offset;
+        let visible = self.lines.iter().enumerate().skip(first_line);
+
+        for (row, (index, line)) in visible.take(self.height).enumerate() {
+            execute!(stdout, MoveTo(0, top + row as u16)).unwrap();
+            let sign = signs
+                .iter()
+                .find(|(line, _)| *line == index)
+                .map(|(_, sign)| *sign)
+                .unwrap_or_default();
+            let sign = format!("{:width$}", sign, width = SIGN_WIDTH as usize);
+            execute!(stdout, PrintStyledContent(theme().separator.apply(sign))).unwrap();
+            let line: String = line.iter().skip(first_col).take(text_width).collect();
+            print!("{}", line);
+        }
+
+        execute!(stdout, MoveTo(0, rows.saturating_sub(1))).unwrap();
+        print!("{}", help);
+
+        let x = SIGN_WIDTH as usize + self.col - first_col;
+        let y = top as usize + self.row - first_line;
+        execute!(stdout, MoveTo(x as u16, y as u16)).unwrap();
+        stdout.flush().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut Editor, code: KeyCode) {
+        editor.handle_key(&KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        text.chars().for_each(|c| press(editor, KeyCode::Char(c)));
+    }
+
+    fn cursor(editor: &Editor) -> (usize, usize) {
+        (editor.row, editor.col)
+    }
+
+    #[test]
+    fn types_at_the_cursor() {
+        let mut editor = Editor::new("ac");
+        press(&mut editor, KeyCode::Right);
+        type_text(&mut editor, "b");
+        assert_eq!(editor.text(), "abc");
+        assert_eq!(cursor(&editor), (0, 2));
+
+        press(&mut editor, KeyCode::Enter);
+        assert_eq!(editor.lines(), ["ab", "c"]);
+        assert_eq!(cursor(&editor), (1, 0));
+    }
+
+    #[test]
+    fn handles_chars_wider_than_a_byte() {
+        let mut editor = Editor::new("åø");
+        press(&mut editor, KeyCode::End);
+        assert_eq!(cursor(&editor), (0, 2));
+        press(&mut editor, KeyCode::Backspace);
+        type_text(&mut editor, "ü");
+        assert_eq!(editor.text(), "åü");
+    }
+
+    #[test]
+    fn backspace_and_delete_join_lines() {
+        let mut editor = Editor::new("front\nback");
+        editor.move_to(1, 0);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.text(), "frontback");
+        assert_eq!(cursor(&editor), (0, 5));
+
+        press(&mut editor, KeyCode::Enter);
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "frontback");
+        assert_eq!(cursor(&editor), (0, 5));
+
+        // Nothing to join at the very start and end.
+        editor.move_to(0, 0);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "frontback");
+    }
+
+    #[test]
+    fn arrows_wrap_around_line_ends() {
+        let mut editor = Editor::new("ab\ncd");
+        editor.move_to(0, 2);
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(cursor(&editor), (1, 0));
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(cursor(&editor), (0, 2));
+
+        editor.move_to(0, 0);
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(cursor(&editor), (0, 0));
+        editor.move_to(1, 2);
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(cursor(&editor), (1, 2));
+    }
+
+    #[test]
+    fn up_and_down_stay_inside_the_line() {
+        let mut editor = Editor::new("a long line\nshort\n");
+        editor.move_to(0, 8);
+        press(&mut editor, KeyCode::Down);
+        assert_eq!(cursor(&editor), (1, 5));
+        press(&mut editor, KeyCode::Down);
+        assert_eq!(cursor(&editor), (2, 0));
+        press(&mut editor, KeyCode::Down);
+        assert_eq!(cursor(&editor), (2, 0));
+        press(&mut editor, KeyCode::PageUp);
+        assert_eq!(cursor(&editor), (1, 0));
+
+        editor.move_to(99, 99);
+        assert_eq!(cursor(&editor), (2, 0));
+    }
+
+    #[test]
+    fn prepending_keeps_the_cursor_on_its_char() {
+        let mut editor = Editor::new("front\nback");
+        editor.move_to(0, 2);
+        editor.prepend("tags: a");
+        assert_eq!(editor.lines()[0], "tags: afront");
+        assert_eq!(cursor(&editor), (0, 9));
+
+        editor.move_to(1, 2);
+        editor.prepend("x");
+        assert_eq!(cursor(&editor), (1, 2));
+    }
+
+    #[test]
+    fn scrolls_to_keep_the_cursor_on_screen() {
+        let mut editor = Editor::new(&"line\n".repeat(20));
+        editor.height = 5;
+        editor.move_to(12, 3);
+        editor.clamp(2);
+        assert_eq!(editor.offset, (8, 2));
+        editor.move_to(3, 0);
+        editor.clamp(2);
+        assert_eq!(editor.offset, (3, 0));
+    }
+
+    #[test]
+    fn ctrl_keys_save_cancel_or_go_to_the_caller() {
+        let mut editor = Editor::new("");
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert!(matches!(editor.handle_key(&ctrl('f')), EditorAction::Save));
+        assert!(matches!(
+            editor.handle_key(&ctrl('q')),
+            EditorAction::Cancel
+        ));
+        assert!(matches!(
+            editor.handle_key(&ctrl('r')),
+            EditorAction::Ctrl('r')
+        ));
+        assert_eq!(editor.text(), "");
+    }
+}
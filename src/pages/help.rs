@@ -0,0 +1,107 @@
+use std::io::Stdout;
+
+use crossterm::event::KeyEvent;
+use speki_backend::card::CardCache;
+
+use crate::app::{Page, PageAction};
+use crate::backend::{get_input, Input};
+use crate::keymap::{keymap, Action, Scope};
+
+use super::listview::{ListAction, ListView};
+use super::{clear_window, move_upper_left};
+
+/// Lists the keys of the page it was opened from, straight from the keymap.
+pub struct HelpPage {
+    scope: Scope,
+    list: ListView,
+}
+
+impl HelpPage {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+            list: ListView::default(),
+        }
+    }
+
+    fn draw(&mut self, stdout: &mut Stdout) {
+        let help = keymap().help(self.scope);
+        let width = help
+            .iter()
+            .map(|(keys, _)| keys.len())
+            .max()
+            .unwrap_or_default();
+        let lines: Vec<String> = help
+            .into_iter()
+            .map(|(keys, description)| format!("{:width$}   {}", keys, description))
+            .collect();
+
+        clear_window(stdout);
+        move_upper_left(stdout);
+        print!("Keys in {}", self.scope);
+        self.list.render(stdout, 1, &lines);
+    }
+
+    /// Whether the key closes the help.
+    fn closes_on(&mut self, key: &KeyEvent) -> bool {
+        match self.list.handle_key(key) {
+            ListAction::Select(_) => true,
+            ListAction::Ignored => matches!(
+                keymap().action(Scope::Menu, key),
+                Some(Action::Quit | Action::Help)
+            ),
+            ListAction::Moved => false,
+        }
+    }
+}
+
+impl Page for HelpPage {
+    fn render(&mut self, stdout: &mut Stdout, _cache: &mut CardCache) {
+        self.draw(stdout);
+    }
+
+    fn handle_key(
+        &mut self,
+        _stdout: &mut Stdout,
+        key: KeyEvent,
+        _cache: &mut CardCache,
+    ) -> PageAction {
+        if self.closes_on(&key) {
+            PageAction::Pop
+        } else {
+            PageAction::None
+        }
+    }
+}
+
+/// Shows the help on its own, for menus and pickers that wait for keys themselves instead of
+/// running as a page.
+pub fn show_help(stdout: &mut Stdout, scope: Scope) {
+    let mut help = HelpPage::new(scope);
+    loop {
+        help.draw(stdout);
+        if let Input::Key(key) = get_input() {
+            if help.closes_on(&key) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn closes_on_quit_and_help() {
+        let mut help = HelpPage::new(Scope::Menu);
+        assert!(!help.closes_on(&key(KeyCode::Char('j'))));
+        assert!(help.closes_on(&key(KeyCode::Char('?'))));
+        assert!(help.closes_on(&key(KeyCode::Esc)));
+    }
+}
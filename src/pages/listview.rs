@@ -0,0 +1,343 @@
+//! Scrollable list that backs every menu and picker.
+//!
+//! Typing a char that isn't a list key starts a fuzzy filter, and `/` starts an empty one.
+//! Typed chars then go to the filter until it's closed with Esc or by erasing it.
+
+use std::io::{Stdout, Write};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    style::{ContentStyle, PrintStyledContent},
+    terminal::{self, Clear, ClearType},
+};
+
+use crate::fuzzy::fuzzy_match;
+use crate::keymap::{keymap, Action, Scope};
+use crate::theme::theme;
+
+pub enum ListAction {
+    /// The selection, the viewport or the filter changed.
+    Moved,
+    /// The user picked the item at this index.
+    Select(usize),
+    /// Not a list key, up to the caller to handle it.
+    Ignored,
+}
+
+const CTRL_OR_ALT: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::ALT);
+
+#[derive(Default)]
+pub struct ListView {
+    /// Items that pass the filter, as indices into the full list, with the matched chars.
+    visible: Vec<(usize, Vec<usize>)>,
+    /// Index into `visible`.
+    selected: usize,
+    /// Index into `visible` of the first row on screen.
+    offset: usize,
+    /// Rows on screen, as of the last render.
+    height: usize,
+    /// The first `g` of `gg` was pressed.
+    pending_g: bool,
+    /// `Some` while the filter is open.
+    filter: Option<String>,
+}
+
+impl ListView {
+    /// Index into the full list of the selected item.
+    pub fn selected(&self) -> Option<usize> {
+        self.visible.get(self.selected).map(|(index, _)| *index)
+    }
+
+    pub fn set_selected(&mut self, index: usize) {
+        self.selected = index;
+        self.clamp();
+    }
+
+    /// Keeps the selection inside the list and the viewport around the selection.
+    fn clamp(&mut self) {
+        let height = self.height.max(1);
+        let len = self.visible.len();
+        self.selected = self.selected.min(len.saturating_sub(1));
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        }
+        if self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+        self.offset = self.offset.min(len.saturating_sub(height));
+    }
+
+    fn select(&self) -> ListAction {
+        match self.selected() {
+            Some(index) => ListAction::Select(index),
+            None => ListAction::Moved,
+        }
+    }
+
+    fn set_filter(&mut self, filter: Option<String>) {
+        self.filter = filter;
+        self.selected = 0;
+        self.offset = 0;
+    }
+
+    fn handle_filter_key(&mut self, key: &KeyEvent) -> Option<ListAction> {
+        let filter = self.filter.as_mut()?;
+
+        match key.code {
+            KeyCode::Char(c) => {
+                filter.push(c);
+                self.selected = 0;
+            }
+            KeyCode::Backspace if filter.is_empty() => self.set_filter(None),
+            KeyCode::Backspace => {
+                filter.pop();
+                self.selected = 0;
+            }
+            KeyCode::Esc => self.set_filter(None),
+            KeyCode::Enter => return Some(self.select()),
+            _ => return None,
+        }
+
+        Some(ListAction::Moved)
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> ListAction {
+        if let Some(action) = self.handle_filter_key(key) {
+            return action;
+        }
+
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let page = self.height.max(1);
+        let last = self.visible.len().saturating_sub(1);
+
+        let is_char = matches!(key.code, KeyCode::Char(_));
+        match keymap().action(Scope::Menu, key) {
+            Some(Action::ListUp) => self.selected = self.selected.saturating_sub(1),
+            Some(Action::ListDown) => self.selected += 1,
+            Some(Action::ListPageUp) => self.selected = self.selected.saturating_sub(page),
+            Some(Action::ListPageDown) => self.selected += page,
+            // Letters are pressed twice, like vim's gg.
+            Some(Action::ListTop) if pending_g || !is_char => self.selected = 0,
+            Some(Action::ListTop) => self.pending_g = true,
+            Some(Action::ListBottom) => self.selected = last,
+            Some(Action::Filter) => self.set_filter(Some(String::new())),
+            Some(Action::Select) => return self.select(),
+            Some(_) => return ListAction::Ignored,
+            None => match key.code {
+                KeyCode::Char(c) if !key.modifiers.intersects(CTRL_OR_ALT) => {
+                    self.set_filter(Some(c.to_string()))
+                }
+                _ => return ListAction::Ignored,
+            },
+        }
+
+        self.clamp();
+        ListAction::Moved
+    }
+
+    fn apply_filter(&mut self, items: &[String]) {
+        let filter = self.filter.as_deref().unwrap_or_default();
+        let mut matches: Vec<(i64, usize, Vec<usize>)> = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                fuzzy_match(filter, item).map(|(score, positions)| (score, index, positions))
+            })
+            .collect();
+
+        // Stable, so items with the same score keep their order.
+        matches.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+
+        self.visible = matches
+            .into_iter()
+            .map(|(_, index, positions)| (index, positions))
+            .collect();
+    }
+
+    /// Draws the items from row `top` down to the bottom of the screen.
+    /// The last row shows the filter and which item is selected.
+    pub fn render(&mut self, stdout: &mut Stdout, top: u16, items: &[String]) {
+        let (_, rows) = terminal::size().unwrap();
+        self.render_rows(stdout, top, rows, items);
+    }
+
+    /// Like [`Self::render`], but leaves the screen from row `rows` down alone.
+    pub fn render_rows(&mut self, stdout: &mut Stdout, top: u16, rows: u16, items: &[String]) {
+        let (width, _) = terminal::size().unwrap();
+        self.height = rows.saturating_sub(top + 1).max(1) as usize;
+        self.apply_filter(items);
+        self.clamp();
+
+        let visible = self.visible.iter().enumerate().skip(self.offset);
+
+        for (row, (idx, (index, positions))) in visible.take(self.height).enumerate() {
+            execute!(stdout, MoveTo(0, top + row as u16)).unwrap();
+            let (prefix, style) = match idx == self.selected {
+                true => ("> ", theme().selection),
+                false => ("  ", ContentStyle::new()),
+            };
+            print_highlighted(
+                stdout,
+                prefix,
+                &items[*index],
+                positions,
+                (width as usize).saturating_sub(2),
+                style,
+            );
+        }
+
+        execute!(
+            stdout,
+            MoveTo(0, rows.saturating_sub(1)),
+            Clear(ClearType::CurrentLine)
+        )
+        .unwrap();
+        if let Some(filter) = &self.filter {
+            print!("/{}  ", filter);
+        }
+        if !self.visible.is_empty() {
+            print!("{} of {}", self.selected + 1, self.visible.len());
+        } else if self.filter.is_some() {
+            print!("no matches");
+        }
+        stdout.flush().unwrap();
+    }
+}
+
+/// Prints at most `width` chars of `item`, with the chars at `positions` highlighted.
+fn print_highlighted(
+    stdout: &mut Stdout,
+    prefix: &str,
+    item: &str,
+    positions: &[usize],
+    width: usize,
+    style: ContentStyle,
+) {
+    let mut highlight = style;
+    highlight.foreground_color = theme().highlight.foreground_color;
+    highlight.attributes.extend(theme().highlight.attributes);
+
+    execute!(stdout, PrintStyledContent(style.apply(prefix))).unwrap();
+
+    for (idx, c) in item.chars().take(width).enumerate() {
+        let style = match positions.contains(&idx) {
+            true => highlight,
+            false => style,
+        };
+        execute!(stdout, PrintStyledContent(style.apply(c))).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn char(c: char) -> KeyEvent {
+        key(KeyCode::Char(c))
+    }
+
+    /// A list of `items` as if it was drawn `height` rows tall.
+    fn list(items: &[String], height: usize) -> ListView {
+        let mut list = ListView {
+            height,
+            ..Default::default()
+        };
+        list.apply_filter(items);
+        list
+    }
+
+    fn items(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn moves_and_stops_at_the_ends() {
+        let items = items(&["a", "b", "c"]);
+        let mut list = list(&items, 10);
+        assert_eq!(list.selected(), Some(0));
+
+        list.handle_key(&key(KeyCode::Up));
+        assert_eq!(list.selected(), Some(0));
+        list.handle_key(&key(KeyCode::Down));
+        list.handle_key(&key(KeyCode::Down));
+        list.handle_key(&key(KeyCode::Down));
+        assert_eq!(list.selected(), Some(2));
+
+        list.handle_key(&char('g'));
+        assert_eq!(list.selected(), Some(2));
+        list.handle_key(&char('g'));
+        assert_eq!(list.selected(), Some(0));
+        list.handle_key(&char('G'));
+        assert_eq!(list.selected(), Some(2));
+    }
+
+    #[test]
+    fn pages_and_keeps_the_selection_on_screen() {
+        let items: Vec<String> = (0..20).map(|idx| idx.to_string()).collect();
+        let mut list = list(&items, 5);
+        list.handle_key(&key(KeyCode::PageDown));
+        assert_eq!(list.selected(), Some(5));
+        assert!(list.offset <= 5 && 5 < list.offset + 5);
+        list.handle_key(&key(KeyCode::End));
+        assert_eq!(list.selected(), Some(19));
+        assert_eq!(list.offset, 15);
+    }
+
+    #[test]
+    fn selects_by_index_into_the_full_list() {
+        let items = items(&["review", "add cards", "view cards"]);
+        let mut list = list(&items, 10);
+        list.handle_key(&key(KeyCode::Down));
+        assert!(matches!(
+            list.handle_key(&key(KeyCode::Enter)),
+            ListAction::Select(1)
+        ));
+    }
+
+    #[test]
+    fn typing_filters_and_resets_the_selection() {
+        let items = items(&["review", "add cards", "view cards", "stats"]);
+        let mut list = list(&items, 10);
+        list.handle_key(&key(KeyCode::Down));
+
+        list.handle_key(&char('v'));
+        list.handle_key(&char('c'));
+        list.apply_filter(&items);
+        assert_eq!(list.filter.as_deref(), Some("vc"));
+        assert_eq!(list.selected(), Some(2));
+        assert_eq!(list.visible.len(), 1);
+        assert!(matches!(
+            list.handle_key(&key(KeyCode::Enter)),
+            ListAction::Select(2)
+        ));
+    }
+
+    #[test]
+    fn closing_the_filter_shows_everything_again() {
+        let items = items(&["review", "stats"]);
+        let mut list = list(&items, 10);
+        list.handle_key(&char('/'));
+        assert_eq!(list.filter.as_deref(), Some(""));
+        list.handle_key(&char('s'));
+        list.handle_key(&key(KeyCode::Esc));
+        list.apply_filter(&items);
+        assert_eq!(list.filter, None);
+        assert_eq!(list.visible.len(), 2);
+    }
+
+    #[test]
+    fn leaves_other_keys_to_the_caller() {
+        let items = items(&["a"]);
+        let mut list = list(&items, 10);
+        assert!(matches!(list.handle_key(&char('q')), ListAction::Ignored));
+        let ctrl_a = KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL);
+        assert!(matches!(list.handle_key(&ctrl_a), ListAction::Ignored));
+        assert_eq!(list.filter, None);
+    }
+}
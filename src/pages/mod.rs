@@ -6,29 +6,44 @@ use std::time::Duration;
 
 use speki_backend::card::{Card, CardCache, IsSuspended, Priority, Reviews, SavedCard};
 use speki_backend::categories::Category;
-use speki_backend::common::duration_to_days;
 use speki_backend::common::{current_time, truncate_string};
 
 use speki_backend::Id;
 
 use ascii_tree::write_tree;
 
-use crossterm::cursor::{self, MoveDown, MoveLeft};
-use crossterm::event::KeyEvent;
-use crossterm::style::Print;
+use crossterm::cursor::{self, MoveLeft};
+use crossterm::style::{
+    Attribute, ContentStyle, Print, PrintStyledContent, SetAttribute, SetStyle,
+};
 use crossterm::terminal;
 use crossterm::{
     cursor::MoveTo,
     event::{read, Event, KeyCode},
     execute,
-    style::{ResetColor, SetForegroundColor},
     terminal::{Clear, ClearType},
 };
 
+use addcards::edit_card_text;
+use help::show_help;
+use listview::{ListAction, ListView};
 use speki_backend::openai::{get_context, get_response};
+
+use crate::cloze;
+use crate::keymap::{keymap, Action, Scope};
+use crate::render::layout::{CardLayout, Scroll};
+use crate::render::{print_lines, render, terminal_width, tint, wrap_text, Line};
+use crate::reverse;
+use crate::stats::{expected_gains, Stats};
+use crate::theme::theme;
+use crate::undo::{undo, Change};
 use tokio::runtime;
 
 pub mod addcards;
+pub mod cram;
+pub mod editor;
+pub mod help;
+pub mod listview;
 pub mod reviewcards;
 pub mod viewcards;
 
@@ -67,7 +82,7 @@ pub fn ascii_test(
 
         for card_id in cards {
             let card = cache.get_ref(&card_id);
-            if item.contains(card.front_text()) {
+            if item.contains(&cloze::question(card.front_text(), card.back_text())) {
                 return Some(cache.get_owned(&card_id));
             }
         }
@@ -76,38 +91,30 @@ pub fn ascii_test(
 }
 
 pub fn print_expected_stuff(stdout: &mut Stdout) {
-    let mut cards: Vec<SavedCard> = SavedCard::load_all_cards()
-        .into_iter()
-        .filter(|card| card.stability().is_some())
+    let s: String = expected_gains()
+        .iter()
+        .map(|card| format!("{}\n", card))
         .collect();
-
-    cards.sort_by_key(|card| (card.expected_gain().unwrap() * 1000.) as i32);
-
-    let mut s = String::new();
-
-    for card in cards {
-        let (Some(gain), Some(stability), Some(recall)) =
-            (card.expected_gain(), card.stability(), card.recall_rate())
-        else {
-            continue;
-        };
-        let gain = (gain * 100.).round() / 100.;
-        let stability = (stability.as_secs_f32() / 864.).round() / 100.;
-        let recall = (recall * 100.).round();
-        let whatever = stability < card.time_since_last_review().unwrap().as_secs_f32() / 86400.;
-
-        s.push_str(&format!(
-            "gain: {}, stability: {}days, recall: {}%, hey: {}, card: {}\n",
-            gain,
-            stability,
-            recall,
-            whatever,
-            card.front_text()
-        ));
-    }
     draw_message(stdout, s.as_str());
 }
 
+/// Reverts the last change to a card and says what it was.
+pub fn undo_last(stdout: &mut Stdout, cache: &mut CardCache) -> Option<Change> {
+    let change = match undo(cache) {
+        Ok(change) => change,
+        Err(e) => {
+            draw_warning(stdout, &format!("couldn't undo: {}", e));
+            return None;
+        }
+    };
+    let message = match &change {
+        Some(change) => format!("undid {}", change.description),
+        None => "nothing to undo".to_string(),
+    };
+    draw_message(stdout, &message);
+    change
+}
+
 pub fn affirmative(stdout: &mut Stdout, question: &str) -> bool {
     match draw_menu(stdout, Some(question), vec!["no", "yes"], false).unwrap() {
         0 => false,
@@ -117,37 +124,7 @@ pub fn affirmative(stdout: &mut Stdout, question: &str) -> bool {
 }
 
 pub fn print_stats(stdout: &mut Stdout, cache: &mut CardCache) {
-    let cards = SavedCard::load_all_cards();
-    let all_cards = cards.len();
-    let mut suspended = 0;
-    let mut finished = 0;
-    let mut pending = 0;
-    let mut strength = 0;
-    let mut reviews = 0;
-    let mut resolved = 0;
-    let mut daily_cards = 0.;
-
-    for card in cards {
-        pending += card.stability().is_none() as i32;
-        reviews += card.reviews().len();
-        finished += card.is_finished() as i32;
-        resolved += card.is_resolved(cache) as i32;
-        strength += (card.strength().unwrap_or_default().as_secs_f32() / 86400.).round() as i32;
-        suspended += card.is_suspended() as i32;
-
-        if let Some(stability) = card.stability() {
-            if card.is_confidently_resolved(cache) {
-                let mut days = 1. / duration_to_days(&stability);
-                // if stability is like 0.1 it still feels dumb to say that it's on average 10 cards a day for one card lol
-                if days > 1.0 {
-                    days = 1.0;
-                }
-                daily_cards += days;
-            }
-        }
-    }
-
-    let output = format!("suspended: {suspended}\nfinished: {finished}\npending: {pending}\nreviews: {reviews}\nstrength: {strength}\nresolved: {resolved}\ndaily cards: {daily_cards}\ntotal cards: {all_cards}");
+    let output = Stats::collect(cache).to_string();
     draw_message(stdout, output.as_str());
     print_expected_stuff(stdout);
 
@@ -249,7 +226,7 @@ pub fn view_dependencies(stdout: &mut Stdout, card: &Id, cache: &mut CardCache)
         let dep = cache.get_ref(&dep);
         msg.push_str(&format!(
             "   {}\tfinished: {}\n",
-            truncate_string(dep.front_text().to_owned(), 50),
+            truncate_string(cloze::question(dep.front_text(), dep.back_text()), 50),
             dep.is_finished(),
         ));
     }
@@ -262,7 +239,7 @@ pub fn view_dependencies(stdout: &mut Stdout, card: &Id, cache: &mut CardCache)
         let dep = cache.get_ref(&dep);
         msg.push_str(&format!(
             "   {}\tfinished: {}\n",
-            truncate_string(dep.front_text().to_owned(), 50),
+            truncate_string(cloze::question(dep.front_text(), dep.back_text()), 50),
             dep.is_finished(),
         ));
     }
@@ -270,40 +247,27 @@ pub fn view_dependencies(stdout: &mut Stdout, card: &Id, cache: &mut CardCache)
     draw_message(stdout, &msg);
 }
 
-pub fn print_the_cool_graphs(stdout: &mut Stdout, data: Vec<Vec<f64>>, message: &str) {
-    let (_, height) = crossterm::terminal::size().unwrap();
-
-    clear_window(stdout);
-    move_upper_left(stdout);
-
-    let output = rasciigraph::plot_many(
-        data,
-        rasciigraph::Config::default().with_height(height as u32 - 4),
-    );
-
-    let output = format!("{}\n_____________\n{}", message, output);
-
-    write_string(stdout, &output);
-
-    read().unwrap();
-}
-
 pub fn print_cool_graph(stdout: &mut Stdout, data: Vec<f64>, message: &str) {
-    let (_, height) = crossterm::terminal::size().unwrap();
-
-    clear_window(stdout);
-    move_upper_left(stdout);
+    loop {
+        let (_, height) = crossterm::terminal::size().unwrap();
 
-    let output = rasciigraph::plot(
-        data,
-        rasciigraph::Config::default().with_height(height as u32 - 4),
-    );
+        clear_window(stdout);
+        move_upper_left(stdout);
 
-    let output = format!("{}\n_____________\n{}", message, output);
+        let output = rasciigraph::plot(
+            data.clone(),
+            rasciigraph::Config::default().with_height(height as u32 - 4),
+        );
 
-    write_string(stdout, &output);
+        write_string(stdout, &format!("{}\n_____________\n", message));
+        execute!(stdout, SetStyle(theme().graph)).unwrap();
+        write_string(stdout, &output);
+        execute!(stdout, SetAttribute(Attribute::Reset)).unwrap();
 
-    read().unwrap();
+        if let Input::Key(_) = get_input() {
+            return;
+        }
+    }
 }
 
 pub fn print_cool_graphs(stdout: &mut Stdout, cache: &mut CardCache) {
@@ -419,132 +383,19 @@ pub fn print_cool_graphs(stdout: &mut Stdout, cache: &mut CardCache) {
     print_cool_graph(stdout, recall_vec, "Recall distribution");
 }
 
-pub fn print_card_review_front(stdout: &mut Stdout, card: &Card, sound: bool) {
-    execute!(stdout, MoveTo(0, 1)).unwrap();
-    println!("{}", card.front.text);
-    if sound {
-        //  card.front.audio.play_audio();
-    }
-}
-
-pub fn print_card_review_back(stdout: &mut Stdout, card: &Card, sound: bool) {
-    move_far_left(stdout);
-    execute!(stdout, MoveDown(1)).unwrap();
-    move_far_left(stdout);
-    println!("===================================================");
-    execute!(stdout, MoveDown(1)).unwrap();
-    move_far_left(stdout);
-    println!("{}", card.back.text);
-    move_far_left(stdout);
-
-    if sound {
-        //card.back.audio.play_audio();
-    }
-}
-
-pub fn view_card_info(stdout: &mut Stdout, card: Arc<SavedCard>) {
-    if card.reviews().is_empty() {
-        return;
-    }
-    let the_current_time = current_time();
-
-    let mut recall_rates: Vec<f64> = vec![];
-    let mut failcall_rates: Vec<f64> = vec![];
-    let mut wincall_rates: Vec<f64> = vec![];
-
-    let win_and_fail_cards = |time: Duration, card: &SavedCard| -> (SavedCard, SavedCard) {
-        let win_card = {
-            let mut thecard = (*card).clone();
-            thecard.fake_new_review(speki_backend::card::Grade::Some, Duration::default(), time);
-            thecard
-        };
-
-        let fail_card = {
-            let mut thecard = (*card).clone();
-            thecard.fake_new_review(speki_backend::card::Grade::Late, Duration::default(), time);
-            thecard
-        };
-
-        (win_card, fail_card)
-    };
-
-    let win_card = {
-        let mut thecard = (*card).clone();
-        thecard.fake_new_review(
-            speki_backend::card::Grade::Some,
-            Duration::default(),
-            current_time(),
-        );
-        thecard
-    };
-
-    let fail_card = {
-        let mut thecard = (*card).clone();
-        thecard.fake_new_review(
-            speki_backend::card::Grade::Late,
-            Duration::default(),
-            current_time(),
-        );
-        thecard
-    };
-
-    for i in 0..200 {
-        let time = the_current_time + Duration::from_secs(1 + 86400 * i);
-        dbg!();
-        let x = card.ml_recall_rate_at_time(time).unwrap() as f64;
-        recall_rates.push(x);
-
-        let x = win_card.ml_recall_rate_at_time(time).unwrap() as f64;
-        dbg!();
-        wincall_rates.push(x);
-        let x = fail_card.ml_recall_rate_at_time(time).unwrap() as f64;
-        dbg!();
-        failcall_rates.push(x);
-    }
-
-    failcall_rates[100] = 1.0;
-
-    //print_cool_graph(stdout, recall_rates, "recall rate hey");
-
-    draw_message(stdout, "this might take a  while...");
-    let mut current_strength: Vec<f64> = vec![];
-    let mut win_strength: Vec<f64> = vec![];
-    let mut fail_strength: Vec<f64> = vec![];
-    let mut gainstuff = vec![];
-    let mut strength_over_time = vec![];
-
-    for i in 0..200 {
-        if i % 2 == 0 {
-            continue;
-        }
-        let time = current_time() + Duration::from_secs(86400 * i);
-
-        let (win_card, fail_card) = win_and_fail_cards(time, &(*card).clone());
-        let x = SavedCard::pure_ml_expected_gain((*card).clone(), win_card, fail_card, time);
-        gainstuff.push(x as f64);
-
-        let x = card.ml_expected_gain_debug(time).unwrap();
-        current_strength.push(duration_to_days(&x.0).into());
-        win_strength.push(duration_to_days(&x.1).into());
-        fail_strength.push(duration_to_days(&x.2).into());
-        strength_over_time.push(duration_to_days(&card.strength_at_time(time).unwrap()) as f64)
-    }
-
-    dbg!(&current_strength, &win_strength, &fail_strength);
-
-    let graphs = vec![current_strength, win_strength, fail_strength];
-    // let graphs = vec![current_strength, fail_strength];
-
-    print_cool_graph(stdout, recall_rates.clone(), "recall rate");
-
-    let recall_rates = vec![recall_rates, wincall_rates, failcall_rates];
-    print_the_cool_graphs(stdout, recall_rates, "some recall rates");
-
-    print_the_cool_graphs(stdout, graphs, "strength over time");
-
-    print_cool_graph(stdout, gainstuff, "gain over time ig");
-
-    print_cool_graph(stdout, strength_over_time, "strength over time");
+/// Lays the card out from row `top` down, with `extra` lines under the back once it's shown.
+pub fn card_layout(card: &Card, show_backside: bool, extra: Vec<Line>, top: u16) -> CardLayout {
+    let width = terminal_width();
+    let (front, back) = cloze::display(&card.front.text, &card.back.text);
+    let mut front = render(&front, width);
+    tint(&mut front, theme().front);
+    let back = show_backside.then(|| {
+        let mut back = render(&back, width);
+        tint(&mut back, theme().back);
+        back.extend(extra);
+        back
+    });
+    CardLayout::new(front, back, top)
 }
 
 async fn generate_answer(card: Arc<SavedCard>, cache: &mut CardCache) {
@@ -609,32 +460,31 @@ where
 /// Bool represents if any action was taken.
 pub fn edit_card(
     stdout: &mut Stdout,
-    key: &KeyCode,
+    action: Action,
     card: Arc<SavedCard>,
     cache: &mut CardCache,
 ) -> bool {
     let mut excluded_cards = HashSet::new();
     excluded_cards.insert(card.id().to_owned());
-    match key {
-        KeyCode::Char('H') => block_on(generate_answer(card, cache)),
-        KeyCode::Char('u') => block_on(fix_question(card, cache)),
-        KeyCode::Char('U') => {
+    match action {
+        Action::GenerateAnswer => block_on(generate_answer(card, cache)),
+        Action::FixQuestion => block_on(fix_question(card, cache)),
+        Action::FixQuestionAndAnswer => {
             block_on(generate_answer(card.clone(), cache));
             block_on(fix_question(card, cache));
         }
-        KeyCode::Char('`') => {
+        Action::ShowInfo => {
             let info = format!("{:?}", card.get_info(cache));
             draw_message(stdout, info.as_str());
-            //view_card_info(stdout, card);
         }
-        KeyCode::Char('p') => {
+        Action::QuickPriority => {
             let ch = _get_char();
             if let Ok(priority) = ch.try_into() {
                 cache.get_owned(card.id()).set_priority(priority);
             }
         }
 
-        KeyCode::Char('P') => {
+        Action::SetPriority => {
             draw_message(stdout, "choose priority, from 0 to 100");
             if let Some(input) = read_user_input(stdout) {
                 if let Ok(num) = input.0.trim().parse::<u32>() {
@@ -644,15 +494,15 @@ pub fn edit_card(
             }
         }
 
-        KeyCode::Char('f') => {
+        Action::MarkFinished => {
             let mut thecard = cache.get_owned(card.id());
             thecard.set_finished(true);
         }
 
-        KeyCode::Char('S') => suspend_card(stdout, card.id(), cache),
+        Action::Suspend => suspend_card(stdout, card.id(), cache),
 
-        KeyCode::Char('g') => {
-            let tags = card.category().get_tags().into_iter().collect();
+        Action::AddTag => {
+            let tags: Vec<String> = card.category().get_tags().into_iter().collect();
             let tag = match pick_item(stdout, "Choose tag", &tags) {
                 Some(tag) => tag,
                 None => return true,
@@ -661,60 +511,122 @@ pub fn edit_card(
             thecard.insert_tag(tag.to_owned());
         }
 
-        KeyCode::Char('y') => {
+        Action::AddDependency => {
             if let Some(chosen_card) = search_for_item(stdout, "Add dependency", excluded_cards) {
+                let change = Change::new("add dependency")
+                    .card(card.id(), cache)
+                    .card(chosen_card.id(), cache);
                 cache
                     .get_owned(card.id())
                     .set_dependency(chosen_card.id(), cache);
+                change.record();
                 cache.refresh();
             }
         }
-        KeyCode::Char('t') => {
+        Action::AddDependent => {
             if let Some(chosen_card) = search_for_item(stdout, "Add dependent", excluded_cards) {
+                let change = Change::new("add dependent")
+                    .card(card.id(), cache)
+                    .card(chosen_card.id(), cache);
                 let info = cache
                     .get_owned(card.id())
                     .set_dependent(chosen_card.id(), cache);
+                change.record();
                 if let Some(info) = info {
                     draw_message(stdout, &info);
                 }
             }
         }
-        KeyCode::Char('M') => {
+        Action::Merge => {
             if let Some(chosen_card) = search_for_item(stdout, "Merge card with...", excluded_cards)
             {
+                let change = Change::new("merge")
+                    .with_neighbours(card.id(), cache)
+                    .with_neighbours(chosen_card.id(), cache);
                 cache
                     .get_owned(card.id())
                     .merge_with(cache, *chosen_card.id());
+                change.record();
                 draw_message(stdout, "ok its merged now :D");
             }
         }
-        KeyCode::Char('v') => {
+        Action::ViewDependencies => {
             view_dependencies(stdout, card.id(), cache);
         }
-        KeyCode::Char('m') => {
+        Action::MoveCard => {
             let folder = match choose_folder(stdout, "Move card to...") {
                 Some(folder) => folder,
                 None => return true,
             };
 
+            let mut change = Change::new("move").card(card.id(), cache);
             let moved_card = cache.get_owned(card.id()).move_card(&folder, cache);
+            change.created(moved_card.as_path());
+            change.record();
             cache.insert(moved_card);
         }
-        KeyCode::Char('e') => {
-            card.edit_with_vim();
+        Action::EditCard => {
+            let mut change = Change::new("edit").card(card.id(), cache);
+            if let Some(reverse) = reverse::sibling(card.id(), cache) {
+                change = change.card(&reverse, cache);
+            }
+            let Some(edited) = edit_card_text(stdout, &card, cache) else {
+                return true;
+            };
+            reverse::sync(&edited, cache);
+            cloze::sync_siblings(card.front_text(), &edited, change, cache);
+        }
+        Action::AddReverse => {
+            match reverse::create(&card, cache) {
+                Some(_) => draw_message(stdout, "reverse card added"),
+                None => draw_warning(
+                    stdout,
+                    "cards with no back, cloze cards and reversed cards can't be reversed",
+                ),
+            };
         }
         _ => return false,
     };
     true
 }
 
-/// Fixes the problem where printing a newline doesn't make the cursor go to the left
+/// Where the card is scrolled after `action`, `None` if it doesn't scroll.
+pub fn scroll_card(action: Action, scroll: Scroll, layout: &CardLayout) -> Option<Scroll> {
+    const STEP: usize = 8;
+    let column = scroll.column;
+    let row = scroll.row;
+    let scroll = match action {
+        Action::ScrollLeft => Scroll {
+            column: column.saturating_sub(STEP),
+            row,
+        },
+        Action::ScrollRight => Scroll {
+            column: (column + STEP).min(layout.max_column()),
+            row,
+        },
+        Action::ScrollUp => Scroll {
+            row: row.saturating_sub(1),
+            column,
+        },
+        Action::ScrollDown => Scroll {
+            row: (row + 1).min(layout.max_row()),
+            column,
+        },
+        _ => return None,
+    };
+    Some(scroll)
+}
+
+/// Word wraps the message to the terminal, and fixes the problem where printing a newline
+/// doesn't make the cursor go to the left
 pub fn write_string(stdout: &mut Stdout, message: &str) {
-    for char in message.chars() {
-        print!("{char}");
-        if char == '\n' {
+    let (column, _) = cursor::position().unwrap();
+    for (idx, row) in wrap_text(message, column as usize).iter().enumerate() {
+        if idx > 0 {
+            println!();
             move_far_left(stdout);
         }
+        print!("{row}");
     }
 }
 
@@ -724,47 +636,35 @@ pub fn draw_menu(
     items: Vec<&str>,
     optional: bool,
 ) -> Option<usize> {
-    let mut selected = 0;
+    let items: Vec<String> = items.into_iter().map(String::from).collect();
+    let mut list = ListView::default();
 
     loop {
-        execute!(stdout, Clear(ClearType::All)).unwrap();
+        clear_window(stdout);
         move_upper_left(stdout);
         if let Some(message) = message {
-            println!("{message}");
+            print!("{message}");
         }
+        list.render(stdout, 1, &items);
 
-        for (index, item) in items.iter().enumerate() {
-            execute!(stdout, MoveTo(0, index as u16 + 1)).unwrap();
+        // Await input from user
+        let event = match get_input() {
+            Input::Key(event) => event,
+            Input::Resize => continue,
+        };
 
-            if index == selected {
-                execute!(stdout, SetForegroundColor(crossterm::style::Color::Blue)).unwrap();
-                println!("> {}", item);
-                execute!(stdout, ResetColor).unwrap();
-            } else {
-                println!("  {}", item);
+        match list.handle_key(&event) {
+            ListAction::Select(selected) => {
+                execute!(stdout, Clear(ClearType::All)).unwrap();
+                execute!(stdout, MoveTo(0, 0)).unwrap();
+                return Some(selected);
             }
-        }
-
-        // Await input from user
-        if let Event::Key(event) = read().unwrap() {
-            match event.code {
-                KeyCode::Up | KeyCode::Char('k') => {
-                    selected = selected.saturating_sub(1);
-                }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if selected < items.len() - 1 {
-                        selected += 1;
-                    }
-                }
-                KeyCode::Char('G') => selected = items.len() - 1,
-                KeyCode::Enter | KeyCode::Char(' ') => {
-                    execute!(stdout, Clear(ClearType::All)).unwrap();
-                    execute!(stdout, MoveTo(0, items.len() as u16 + 1)).unwrap();
-                    return Some(selected);
-                }
-                KeyCode::Char('q') | KeyCode::Esc if optional => return None,
+            ListAction::Ignored => match keymap().action(Scope::Menu, &event) {
+                Some(Action::Quit) if optional => return None,
+                Some(Action::Help) => show_help(stdout, Scope::Menu),
                 _ => {}
-            }
+            },
+            ListAction::Moved => {}
         }
     }
 }
@@ -777,33 +677,45 @@ pub fn move_far_left(stdout: &mut Stdout) {
 pub fn update_status_bar(stdout: &mut Stdout, msg: &str) {
     let pre_pos = cursor::position().unwrap();
     execute!(stdout, MoveTo(0, 0)).unwrap();
-    writeln!(stdout, "{}", msg).unwrap();
+    execute!(stdout, PrintStyledContent(theme().status_bar.apply(msg))).unwrap();
     stdout.flush().unwrap();
     execute!(stdout, cursor::MoveTo(pre_pos.0, pre_pos.1)).unwrap();
 }
 
-pub fn draw_key_event_message(stdout: &mut Stdout, message: &str) -> KeyEvent {
-    execute!(stdout, MoveTo(0, 0)).unwrap();
-
-    execute!(stdout, Clear(ClearType::All)).unwrap();
-    write_string(stdout, message);
-    execute!(stdout, ResetColor).unwrap();
-
-    let pressed_char = get_key_event();
+pub fn draw_message(stdout: &mut Stdout, message: &str) -> KeyCode {
+    draw_styled(stdout, message, ContentStyle::new())
+}
 
-    execute!(stdout, Clear(ClearType::All)).unwrap();
+/// Opens the text in `$EDITOR`, telling the user if it couldn't be started. `None` if it was
+/// cancelled or failed.
+pub fn edit_in_editor(stdout: &mut Stdout, text: String, vim_rc: Option<&str>) -> Option<String> {
+    match get_text_from_editor(Some(text), vim_rc) {
+        Ok(text) => text,
+        Err(e) => {
+            draw_warning(stdout, &format!("couldn't edit the text in $EDITOR: {}", e));
+            None
+        }
+    }
+}
 
-    pressed_char
+/// Like [`draw_message`], for when something went wrong.
+pub fn draw_warning(stdout: &mut Stdout, message: &str) -> KeyCode {
+    draw_styled(stdout, message, theme().warning)
 }
 
-pub fn draw_message(stdout: &mut Stdout, message: &str) -> KeyCode {
-    execute!(stdout, MoveTo(0, 0)).unwrap();
+fn draw_styled(stdout: &mut Stdout, message: &str, style: ContentStyle) -> KeyCode {
+    let pressed_char = loop {
+        execute!(stdout, MoveTo(0, 0)).unwrap();
 
-    execute!(stdout, Clear(ClearType::All)).unwrap();
-    write_string(stdout, message);
-    execute!(stdout, ResetColor).unwrap();
+        execute!(stdout, Clear(ClearType::All)).unwrap();
+        execute!(stdout, SetStyle(style)).unwrap();
+        write_string(stdout, message);
+        execute!(stdout, SetAttribute(Attribute::Reset)).unwrap();
 
-    let pressed_char = get_keycode();
+        if let Input::Key(event) = get_input() {
+            break event.code;
+        }
+    };
 
     execute!(stdout, Clear(ClearType::All)).unwrap();
 
@@ -823,7 +735,7 @@ pub fn choose_folder(stdout: &mut Stdout, message: &str) -> Option<Category> {
 pub fn pick_item<'a, T: Display>(
     stdout: &mut Stdout,
     message: &str,
-    items: &'a Vec<T>,
+    items: &'a [T],
 ) -> Option<&'a T> {
     let formatter = |item: &T| format!("{}", item);
     pick_item_with_formatter(stdout, message, items, formatter)
@@ -832,7 +744,7 @@ pub fn pick_item<'a, T: Display>(
 pub fn pick_item_with_formatter<'a, T, F>(
     stdout: &mut Stdout,
     message: &str,
-    items: &'a Vec<T>,
+    items: &'a [T],
     formatter: F,
 ) -> Option<&'a T>
 where
@@ -842,41 +754,29 @@ where
         draw_message(stdout, "list is empty");
         return None;
     }
-    let mut selected = 0;
+    let lines: Vec<String> = items.iter().map(formatter).collect();
+    let mut list = ListView::default();
 
     loop {
-        execute!(stdout, Clear(ClearType::All)).unwrap();
-        execute!(stdout, MoveTo(0, 0)).unwrap();
+        clear_window(stdout);
+        move_upper_left(stdout);
         print!("{}", message);
-
-        for (index, item) in items.iter().enumerate() {
-            execute!(stdout, MoveTo(0, (index + 1) as u16)).unwrap();
-
-            if index == selected {
-                execute!(stdout, SetForegroundColor(crossterm::style::Color::Blue)).unwrap();
-                println!("> {}", formatter(item));
-                execute!(stdout, ResetColor).unwrap();
-            } else {
-                println!("  {}", formatter(item));
-            }
-        }
+        list.render(stdout, 1, &lines);
 
         // Await input from user
-        if let Event::Key(event) = read().unwrap() {
-            match event.code {
-                KeyCode::Up | KeyCode::Char('k') => {
-                    selected = selected.saturating_sub(1);
-                }
-                KeyCode::Char('G') => selected = items.len() - 1,
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if selected < items.len() - 1 {
-                        selected += 1;
-                    }
-                }
-                KeyCode::Enter | KeyCode::Char(' ') => return Some(&items[selected]),
-                key if should_exit(&key) => return None,
+        let event = match get_input() {
+            Input::Key(event) => event,
+            Input::Resize => continue,
+        };
+
+        match list.handle_key(&event) {
+            ListAction::Select(selected) => return Some(&items[selected]),
+            ListAction::Ignored => match keymap().action(Scope::Menu, &event) {
+                Some(Action::Quit) => return None,
+                Some(Action::Help) => show_help(stdout, Scope::Menu),
                 _ => {}
-            }
+            },
+            ListAction::Moved => {}
         }
     }
 }
@@ -889,70 +789,59 @@ pub fn search_for_item(
     let mut input = String::new();
 
     let cards = SavedCard::load_all_cards();
-    let mut index = 0;
+    let mut list = ListView::default();
+
+    loop {
+        let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
+        let lines: Vec<String> = the_cards
+            .iter()
+            .map(|card| {
+                cloze::question(card.front_text(), card.back_text())
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned()
+            })
+            .collect();
 
-    let mut print_stuff = |search_term: &str, cards: Vec<&SavedCard>, index: &mut usize| {
         clear_window(stdout);
-        //move_upper_left(stdout);
-        execute!(stdout, MoveTo(0, 0)).unwrap();
+        move_upper_left(stdout);
         println!("{}", message);
-        println!("\t\t| {} |", search_term);
-        let screen_height = crossterm::terminal::size().unwrap().1 - 10;
-        *index = std::cmp::min(
-            std::cmp::min(*index, screen_height.into()),
-            cards.len().saturating_sub(1),
-        );
-        for (idx, card) in cards.iter().enumerate() {
-            move_far_left(stdout);
+        move_far_left(stdout);
+        print!("\t\t| {} |", input);
+
+        // The list takes the top half, the selected card's front is shown in full below it.
+        let (_, rows) = terminal::size().unwrap();
+        let preview_top = rows / 2;
+        list.render_rows(stdout, 2, preview_top, &lines);
+        if let Some(card) = list.selected().map(|index| the_cards[index]) {
+            let question = cloze::question(card.front_text(), card.back_text());
+            let mut preview = render(&question, terminal_width());
+            preview.truncate(rows.saturating_sub(preview_top + 1) as usize);
+            execute!(stdout, MoveTo(0, preview_top)).unwrap();
+            print_lines(stdout, &preview, 0);
+        }
+
+        let event = match get_input() {
+            Input::Key(event) => event,
+            Input::Resize => continue,
+        };
 
-            if idx == *index {
-                execute!(stdout, SetForegroundColor(crossterm::style::Color::Blue)).unwrap();
-                println!("> {}", card.front_text());
-                execute!(stdout, ResetColor).unwrap();
-            } else {
-                println!("  {}", card.front_text());
+        match event.code {
+            KeyCode::Char(c) => {
+                input.push(c);
+                list.set_selected(0);
             }
-
-            if idx == screen_height as usize {
-                break;
+            KeyCode::Backspace => {
+                input.pop();
+                list.set_selected(0);
             }
-        }
-    };
-
-    loop {
-        if let Event::Key(event) = read().unwrap() {
-            match event.code {
-                KeyCode::Char(c) => {
-                    input.push(c);
-                    let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
-                    print_stuff(&input, the_cards, &mut index);
-                }
-                KeyCode::Backspace if !input.is_empty() => {
-                    input.pop();
-                    let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
-                    print_stuff(&input, the_cards, &mut index);
-                }
-                KeyCode::Enter => {
-                    let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
-                    if the_cards.is_empty() {
-                        return None;
-                    }
-                    return Some(the_cards[index].to_owned());
-                }
-                KeyCode::Down => {
-                    index += 1;
-
-                    let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
-                    print_stuff(&input, the_cards, &mut index);
-                }
-                KeyCode::Up => {
-                    let the_cards = SavedCard::search_in_cards(&input, &cards, &excluded_cards);
-                    index = index.saturating_sub(1);
-                    print_stuff(&input, the_cards, &mut index);
-                }
-                KeyCode::Esc => return None,
+            KeyCode::Esc => return None,
+            _ => match list.handle_key(&event) {
+                ListAction::Select(index) => return Some(the_cards[index].to_owned()),
+                ListAction::Ignored if event.code == KeyCode::Enter => return None,
                 _ => {}
-            }
+            },
         }
     }
 }
@@ -998,5 +887,5 @@ pub fn read_user_input(stdout: &mut Stdout) -> Option<(String, KeyCode)> {
 use std::io::{Stdout, Write};
 
 use crate::backend::{
-    _get_char, cards_as_string, get_key_event, get_keycode, should_exit, to_ascii_tree,
+    _get_char, cards_as_string, get_input, get_text_from_editor, to_ascii_tree, Input,
 };
@@ -2,180 +2,566 @@ use std::collections::BTreeSet;
 
 use std::io::Stdout;
 
-use std::ops::ControlFlow;
 use std::time::Duration;
 
-use speki_backend::card::{CardCache, ReviewType, SavedCard};
+use speki_backend::card::{CardCache, Grade, ReviewType, SavedCard};
 use speki_backend::categories::Category;
 
 use speki_backend::common::current_time;
 
 use speki_backend::Id;
 
-use rand::seq::SliceRandom;
-
 use crossterm::{
-    event::KeyCode,
+    cursor::{Hide, MoveTo, Show},
+    event::KeyEvent,
     execute,
-    terminal::{Clear, ClearType},
+    style::ContentStyle,
+    terminal::{self, Clear, ClearType},
 };
 
-use crate::backend::{get_keycode, should_exit, CardsFromCategory};
+use crate::app::{Page, PageAction};
+use crate::backend::CardsFromCategory;
+use crate::cloze;
+use crate::config::config;
+use crate::diff::{diff, suggest_grade, Edit};
+use crate::keymap::{keymap, Action, Scope};
+use crate::limits::{self, Quota};
+use crate::queue::{self, Order};
+use crate::render::layout::{CardLayout, Scroll};
+use crate::render::{terminal_width, wrap, Line, Span};
+use crate::reverse;
+use crate::session::Session;
+use crate::theme::theme;
+use crate::undo::Change;
 
 use super::addcards::{add_card, add_dependency, add_dependent};
-use super::viewcards::view_all_cards;
+use super::help::HelpPage;
+use super::viewcards::ViewCardsPage;
 use super::{
-    affirmative, ascii_test, draw_message, edit_card, print_card_review_back,
-    print_card_review_front, update_status_bar,
+    affirmative, ascii_test, card_layout, draw_message, draw_warning, edit_card, read_user_input,
+    scroll_card, undo_last, update_status_bar, write_string,
 };
 
-pub fn review_cards(
-    stdout: &mut Stdout,
+/// A review session that ends once `budget` has passed since `started`.
+struct Timebox {
+    started: Duration,
+    budget: Duration,
+}
+
+impl Timebox {
+    fn elapsed(&self) -> Duration {
+        current_time().saturating_sub(self.started)
+    }
+
+    fn is_over(&self) -> bool {
+        self.elapsed() >= self.budget
+    }
+}
+
+fn minutes_and_seconds(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+pub struct ReviewPage {
     category: Category,
-    mut get_cards: CardsFromCategory,
-    cache: &mut CardCache,
+    get_cards: CardsFromCategory,
     toggle_refresh: bool,
-    randomized: bool,
-) {
-    loop {
-        let categories = category.get_following_categories();
+    order: Order,
+    /// `None` until the queue has been loaded for the first time.
+    cards: Option<Vec<Id>>,
+    index: usize,
+    show_backside: bool,
+    start_time: Duration,
+    duration: Duration,
+    /// Page to continue with once this one is done, with a message to show in between.
+    then: Option<(&'static str, Box<ReviewPage>)>,
+    session: Session,
+    /// The daily cap the queue is held to, if any.
+    quota: Option<Quota>,
+    timebox: Option<Timebox>,
+    /// Cards left out of this session since a card they depend on was failed.
+    deferred: BTreeSet<Id>,
+    /// Ask for the answer before showing the back of the card.
+    typed_answers: bool,
+    /// How the typed answer differs from the card, with the grade that suggests.
+    answer: Option<(Vec<Edit>, char)>,
+    /// How far the card is scrolled.
+    scroll: Scroll,
+}
+
+impl ReviewPage {
+    pub fn new(
+        category: Category,
+        get_cards: CardsFromCategory,
+        toggle_refresh: bool,
+        order: Order,
+    ) -> Self {
+        Self {
+            category,
+            get_cards,
+            toggle_refresh,
+            order,
+            cards: None,
+            index: 0,
+            show_backside: false,
+            start_time: current_time(),
+            duration: Duration::default(),
+            then: None,
+            session: Session::default(),
+            quota: None,
+            timebox: None,
+            deferred: BTreeSet::new(),
+            typed_answers: config().review.typed_answers,
+            answer: None,
+            scroll: Scroll::default(),
+        }
+    }
+
+    /// Reviews the due cards that give the most per second spent, until `budget` is used up.
+    pub fn timeboxed(category: Category, budget: Duration) -> Self {
+        let mut page = Self::new(
+            category,
+            Box::new(Category::get_review_cards),
+            true,
+            Order::GainPerSecond,
+        );
+        page.timebox = Some(Timebox {
+            started: current_time(),
+            budget,
+        });
+        page
+    }
+
+    /// The due cards within today's review cap, then the pending cards within the new card cap.
+    pub fn normal(category: Category) -> Self {
+        Self::ordered(category, config().review.order)
+    }
+
+    /// Like [`Self::normal`], in the given order instead of the configured one.
+    pub fn ordered(category: Category, order: Order) -> Self {
+        let pending = Self::new(
+            category.clone(),
+            Box::new(Category::get_pending_cards),
+            true,
+            order,
+        )
+        .with_quota(Quota::New);
+
+        Self::new(category, Box::new(Category::get_review_cards), true, order)
+            .with_quota(Quota::Reviews)
+            .then("now reviewing pending cards", Box::new(pending))
+    }
+
+    pub fn with_quota(mut self, quota: Quota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// The next page takes over the session, so the summary covers both.
+    pub fn then(mut self, message: &'static str, next: Box<ReviewPage>) -> Self {
+        self.then = Some((message, next));
+        self
+    }
+
+    fn load_cards(&mut self, cache: &mut CardCache) {
+        let categories = self.category.get_following_categories();
         let mut cards = BTreeSet::new();
         for category in &categories {
-            cards.extend(get_cards(category, cache));
+            cards.extend((self.get_cards)(category, cache));
         }
 
-        let mut cards: Vec<Id> = cards.into_iter().collect();
+        let cards: Vec<Id> = cards.difference(&self.deferred).copied().collect();
+        let mut cards = queue::sort(cards, self.order, cache);
 
-        let cardqty = cards.len();
+        if config().review.separate_reverse_cards {
+            cards = reverse::keep_apart(cards, &self.session.reviewed(), cache);
+        }
 
-        if randomized {
-            let mut rng = rand::thread_rng();
-            cards.shuffle(&mut rng);
-        } else {
-            cards.sort_by_key(|card| {
-                (cache.get_ref(card).expected_gain().unwrap_or_default() * 1000.) as i32
-            });
-            cards.reverse();
+        if let Some(quota) = self.quota {
+            cards = limits::apply(quota, cards, cache);
         }
 
-        if cardqty == 0 {
-            draw_message(stdout, "Nothing to review!");
-            return;
+        self.cards = Some(cards);
+        self.index = 0;
+        self.show_card(cache);
+    }
+
+    fn current_card(&self) -> Option<Id> {
+        self.cards.as_ref()?.get(self.index).copied()
+    }
+
+    /// Resets the per-card state, skipping cards that can't be reviewed.
+    fn show_card(&mut self, cache: &mut CardCache) {
+        while let Some(card) = self.current_card() {
+            if cache.exists(&card)
+                && !matches!(
+                    cache.get_ref(&card).get_review_type(),
+                    ReviewType::Unfinished
+                )
+            {
+                break;
+            }
+            self.index += 1;
         }
+        self.reset_card_state();
+    }
 
-        for (index, card) in cards.into_iter().enumerate() {
-            let info = cache.get_ref(&card).get_info(cache).unwrap_or_default();
-            let status = format!(
-                "{}/{}\t{}\t{}/{}/{}/{}/{}",
-                index,
-                cardqty,
-                cache.get_ref(&card).category().print_full(),
-                cache.dependencies(&card).len(),
-                cache.dependents(&card).len(),
-                (info.recall_rate * 100.).round(),
-                (info.stability * 100.).round() / 100.,
-                info.strength.round(),
-            );
+    fn reset_card_state(&mut self) {
+        self.show_backside = false;
+        self.start_time = current_time();
+        self.duration = Duration::default();
+        self.answer = None;
+        self.scroll = Scroll::default();
+    }
 
-            match {
-                match cache.get_ref(&card).get_review_type() {
-                    ReviewType::Normal | ReviewType::Pending => {
-                        review_card(stdout, &card, status.clone(), cache)
-                    }
+    /// The card with the typed answer under its back.
+    fn layout(&self, card: &SavedCard) -> CardLayout {
+        let extra = match &self.answer {
+            Some((edits, _)) => diff_lines(edits),
+            None => vec![],
+        };
+        card_layout(card.card_as_ref(), self.show_backside, extra, 1)
+    }
 
-                    ReviewType::Unfinished => continue,
-                }
-            } {
-                ControlFlow::Continue(()) => {
-                    continue;
+    /// Asks for the answer on the bottom line, `false` if the user backed out.
+    fn type_answer(&mut self, stdout: &mut Stdout, card: &SavedCard) -> bool {
+        let (_, rows) = terminal::size().unwrap();
+        execute!(stdout, MoveTo(0, rows.saturating_sub(1)), Show).unwrap();
+        write_string(stdout, "answer: ");
+        let input = read_user_input(stdout);
+        execute!(stdout, Hide).unwrap();
+
+        let Some((typed, _)) = input else {
+            return false;
+        };
+        let expected = cloze::expected_answer(card.front_text(), card.back_text());
+        let suggested = suggest_grade(&typed, &expected);
+        self.answer = Some((diff(&typed, &expected), suggested));
+        true
+    }
+
+    fn grade(
+        &mut self,
+        stdout: &mut Stdout,
+        card_id: Id,
+        key: char,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        let Ok(grade) = key.to_string().parse::<Grade>() else {
+            return PageAction::None;
+        };
+
+        let card = cache.get_ref(&card_id);
+        let failed = matches!(grade, Grade::None | Grade::Late);
+        let change = Change::new("review")
+            .card(&card_id, cache)
+            .reviewed(card_id);
+        self.session.review(&card_id, key, self.duration, cache);
+        let new = matches!(card.get_review_type(), ReviewType::Pending);
+        limits::record_review(card.category(), new);
+        cache.get_owned(&card_id).new_review(grade, self.duration);
+        change.record();
+        if failed && matches!(self.order, Order::Dependencies) {
+            self.defer_dependents(&card_id, cache);
+        }
+        self.next_card(stdout, cache)
+    }
+
+    fn next_card(&mut self, stdout: &mut Stdout, cache: &mut CardCache) -> PageAction {
+        if self.timebox.as_ref().is_some_and(Timebox::is_over) {
+            draw_warning(stdout, "Time's up!");
+            return self.finish(stdout, cache);
+        }
+
+        self.index += 1;
+        self.show_card(cache);
+
+        if self.current_card().is_some() {
+            return PageAction::None;
+        }
+
+        if self.toggle_refresh {
+            self.load_cards(cache);
+            PageAction::None
+        } else {
+            self.finish(stdout, cache)
+        }
+    }
+
+    fn finish(&mut self, stdout: &mut Stdout, cache: &mut CardCache) -> PageAction {
+        match self.then.take() {
+            Some((message, mut next)) => {
+                draw_message(stdout, message);
+                next.session = std::mem::take(&mut self.session);
+                next.deferred = std::mem::take(&mut self.deferred);
+                PageAction::Replace(next)
+            }
+            None => {
+                if !self.session.is_empty() {
+                    let summary = self.session.summary(cache);
+                    summary.append_to_log();
+                    draw_message(stdout, &summary.to_string());
                 }
-                ControlFlow::Break(()) => return,
+                PageAction::Pop
             }
         }
-        if !toggle_refresh {
-            return;
+    }
+
+    /// Takes the dependents of a failed card out of the session, there's no point reviewing them
+    /// until it's been learned again.
+    fn defer_dependents(&mut self, card: &Id, cache: &mut CardCache) {
+        let dependents = cache.recursive_dependents(card);
+        if let Some(cards) = self.cards.as_mut() {
+            let upcoming = cards.split_off((self.index + 1).min(cards.len()));
+            cards.extend(
+                upcoming
+                    .into_iter()
+                    .filter(|card| !dependents.contains(card)),
+            );
         }
+        self.deferred.extend(dependents);
     }
-}
 
-pub fn print_card_for_review(
-    stdout: &mut Stdout,
-    card: &SavedCard,
-    show_backside: bool,
-    status: &str,
-) {
-    execute!(stdout, Clear(ClearType::All)).unwrap();
-    update_status_bar(stdout, status);
-    print_card_review_front(stdout, card.card_as_ref(), true);
-    if show_backside {
-        print_card_review_back(stdout, card.card_as_ref(), true);
+    /// Steps back to a card whose review was undone.
+    fn review_again(&mut self, card: Id) {
+        self.session.undo_review(&card);
+        let cards = self.cards.get_or_insert_with(Vec::new);
+        match cards[..self.index.min(cards.len())]
+            .iter()
+            .rposition(|id| *id == card)
+        {
+            Some(index) => self.index = index,
+            None => cards.insert(self.index.min(cards.len()), card),
+        }
+        self.reset_card_state();
+    }
+
+    fn status(&self, card: &Id, cache: &mut CardCache) -> String {
+        let cardqty = self.cards.as_ref().map(Vec::len).unwrap_or_default();
+        let info = cache.get_ref(card).get_info(cache).unwrap_or_default();
+        let quota = self
+            .quota
+            .and_then(|quota| limits::remaining(quota, cache.get_ref(card).category()))
+            .map(|left| format!("\t{} left today", left))
+            .unwrap_or_default();
+        let time = self
+            .timebox
+            .as_ref()
+            .map(|timebox| {
+                format!(
+                    "\t{} elapsed, {} left",
+                    minutes_and_seconds(timebox.elapsed()),
+                    minutes_and_seconds(timebox.budget.saturating_sub(timebox.elapsed()))
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "{}/{}\t{}\t{}/{}/{}/{}/{}{}{}",
+            self.index,
+            cardqty,
+            cache.get_ref(card).category().print_full(),
+            cache.dependencies(card).len(),
+            cache.dependents(card).len(),
+            (info.recall_rate * 100.).round(),
+            (info.stability * 100.).round() / 100.,
+            info.strength.round(),
+            quota,
+            time,
+        )
     }
 }
 
-pub fn review_card(
-    stdout: &mut Stdout,
-    card_id: &Id,
-    status: String,
-    cache: &mut CardCache,
-) -> ControlFlow<()> {
-    let mut show_backside = false;
-    let start_time = current_time();
-    let mut duration = Duration::default();
-    loop {
-        let card = cache.get_ref(card_id);
-        print_card_for_review(stdout, &card, show_backside, status.as_str());
-        let keycode = get_keycode();
-        if edit_card(stdout, &keycode, card.clone(), cache) {
-            continue;
-        }
-        match keycode {
-            KeyCode::Char('o') => {
-                view_all_cards(stdout, cache);
+impl Page for ReviewPage {
+    fn render(&mut self, stdout: &mut Stdout, cache: &mut CardCache) {
+        if self.cards.is_none() {
+            self.load_cards(cache);
+        }
+
+        let Some(card) = self.current_card() else {
+            execute!(stdout, Clear(ClearType::All)).unwrap();
+            write_string(stdout, "Nothing to review!");
+            return;
+        };
+
+        let mut status = self.status(&card, cache);
+        if let Some((_, suggested)) = &self.answer {
+            status.push_str(&format!(
+                "\tsuggested grade: {}, {} to accept",
+                suggested,
+                keymap().keys(Action::AcceptGrade)
+            ));
+        }
+        let layout = self.layout(&cache.get_ref(&card));
+        print_card_for_review(stdout, &layout, self.scroll, &status);
+    }
+
+    fn handle_key(
+        &mut self,
+        stdout: &mut Stdout,
+        key: KeyEvent,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        let Some(card_id) = self.current_card() else {
+            return self.finish(stdout, cache);
+        };
+
+        let card = cache.get_ref(&card_id);
+
+        let Some(action) = keymap().action(Scope::Review, &key) else {
+            return PageAction::None;
+        };
+
+        let grade = match (action, &self.answer) {
+            (Action::AcceptGrade, Some((_, suggested))) => Some(*suggested),
+            _ => action.grade(),
+        };
+        if let Some(grade) = grade {
+            return match self.show_backside {
+                true => self.grade(stdout, card_id, grade, cache),
+                false => PageAction::None,
+            };
+        }
+
+        if edit_card(stdout, action, card.clone(), cache) {
+            return PageAction::None;
+        }
+
+        if let Some(scroll) = scroll_card(action, self.scroll, &self.layout(&card)) {
+            self.scroll = scroll;
+            return PageAction::None;
+        }
+
+        match action {
+            Action::Help => return PageAction::Push(Box::new(HelpPage::new(Scope::Review))),
+            Action::OpenBrowser => {
+                return PageAction::Push(Box::new(ViewCardsPage::all_cards(cache)));
             }
-            KeyCode::Char('X') => {
+            Action::DependencyTree => {
                 let _ = ascii_test(stdout, card.id(), cache, true);
             }
-            KeyCode::Char('x') => {
+            Action::DependentTree => {
                 let _ = ascii_test(stdout, card.id(), cache, false);
             }
-            KeyCode::Char('Y') => {
+            Action::NewDependency => {
                 draw_message(stdout, "Adding new dependency");
                 let category = Some(card.category().to_owned());
                 add_dependency(stdout, card.id(), category.as_ref(), cache);
             }
-            KeyCode::Char('T') => {
+            Action::NewDependent => {
                 draw_message(stdout, "Adding new dependent");
                 let category = Some(card.category().to_owned());
                 add_dependent(stdout, card.id(), category.as_ref(), cache);
             }
-            KeyCode::Char('q') => return ControlFlow::Break(()),
-            KeyCode::Char('D') => {
-                if affirmative(stdout, "Delete card?") {
-                    cache.get_owned(card.id()).delete(cache);
-                    draw_message(stdout, "Card deleted");
-                    break;
+            Action::Quit => return self.finish(stdout, cache),
+            Action::Delete if affirmative(stdout, "Delete card?") => {
+                let change = Change::new("delete").with_neighbours(card.id(), cache);
+                cache.get_owned(card.id()).delete(cache);
+                change.record();
+                draw_message(stdout, "Card deleted");
+                return self.next_card(stdout, cache);
+            }
+            Action::Reveal => {
+                if !self.show_backside {
+                    if self.typed_answers && !self.type_answer(stdout, &card) {
+                        return PageAction::None;
+                    }
+                    self.duration = current_time() - self.start_time;
                 }
+                self.show_backside = true;
+            }
+            Action::TypeAnswer => {
+                self.typed_answers = !self.typed_answers;
+                let state = if self.typed_answers { "on" } else { "off" };
+                draw_message(stdout, &format!("typed answers {}", state));
             }
-            KeyCode::Char(' ') => {
-                if !show_backside {
-                    duration = current_time() - start_time;
+            Action::Skip => return self.next_card(stdout, cache),
+            Action::Undo => {
+                if let Some(Change {
+                    reviewed: Some(reviewed),
+                    ..
+                }) = undo_last(stdout, cache)
+                {
+                    let card = cache.get_ref(&reviewed);
+                    let new = matches!(card.get_review_type(), ReviewType::Pending);
+                    limits::forget_review(card.category(), new);
+                    self.review_again(reviewed);
                 }
-                show_backside = true;
             }
-            KeyCode::Char('s') => break,
-            KeyCode::Char('a') => {
-                add_card(None, &mut card.category().to_owned(), cache);
+            Action::AddCard => {
+                add_card(stdout, None, &mut card.category().to_owned(), cache);
             }
-            KeyCode::Char(c) if show_backside => match c.to_string().parse() {
-                Ok(grade) => {
-                    cache.get_owned(card_id).new_review(grade, duration);
-                    return ControlFlow::Continue(());
+            _ => {}
+        }
+        PageAction::None
+    }
+
+    fn on_resume(&mut self, _stdout: &mut Stdout, cache: &mut CardCache) {
+        // The card might have been deleted or merged away from a page we opened.
+        if let Some(card) = self.current_card() {
+            if !cache.exists(&card) {
+                self.index += 1;
+                self.show_card(cache);
+                if self.current_card().is_none() && self.toggle_refresh {
+                    self.load_cards(cache);
                 }
-                _ => continue,
-            },
-            key if should_exit(&key) => return ControlFlow::Break(()),
-            _ => continue,
+            }
         }
     }
-    ControlFlow::Continue(())
+}
+
+/// The typed answer with what was missing and what shouldn't be there marked.
+fn diff_lines(edits: &[Edit]) -> Vec<Line> {
+    let mut answer = Line::default();
+    for edit in edits {
+        let (c, style) = match edit {
+            Edit::Same(c) => (*c, ContentStyle::new()),
+            Edit::Missing(c) => (*c, theme().missing),
+            Edit::Extra(c) => (*c, theme().extra),
+        };
+        answer.push(c, style);
+    }
+
+    let mut lines = vec![
+        Line::default(),
+        Line::new(vec![Span::plain("your answer:")]),
+    ];
+    let none = || Span::plain("");
+    lines.extend(wrap(answer.spans, terminal_width(), none(), none()));
+    lines
+}
+
+pub fn print_card_for_review(
+    stdout: &mut Stdout,
+    layout: &CardLayout,
+    scroll: Scroll,
+    status: &str,
+) {
+    execute!(stdout, Clear(ClearType::All)).unwrap();
+    update_status_bar(stdout, status);
+    layout.draw(stdout, scroll);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{set_config, ReviewSettings, TuiConfig};
+
+    #[test]
+    fn reads_the_review_settings_from_the_config() {
+        set_config(TuiConfig {
+            review: ReviewSettings {
+                typed_answers: true,
+                order: Order::Interleaved,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let page = ReviewPage::normal(Category::import_category());
+        assert!(page.typed_answers);
+        assert!(matches!(page.order, Order::Interleaved));
+        let (_, pending) = page.then.as_ref().unwrap();
+        assert!(pending.typed_answers);
+    }
 }
@@ -7,125 +7,177 @@ use speki_backend::card::CardCache;
 
 use speki_backend::Id;
 
-use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
 
-use crate::backend::should_exit;
+use crate::app::{Page, PageAction};
+use crate::keymap::{keymap, Action, Scope};
+use crate::render::layout::Scroll;
+use crate::undo::Change;
 
 use super::addcards::{add_card, add_dependency, add_dependent};
+use super::help::HelpPage;
 use super::{
-    affirmative, ascii_test, draw_key_event_message, draw_message, edit_card, fix_question,
-    generate_answer, search_for_item,
+    affirmative, ascii_test, card_layout, clear_window, draw_message, draw_warning, edit_card,
+    fix_question, generate_answer, move_upper_left, scroll_card, search_for_item, undo_last,
+    write_string,
 };
 
-//pub fn view_card(stdout: &mut Stdout, mut cards: Vec<Id>, cache: &mut CardCache) {}
+pub struct ViewCardsPage {
+    cards: Vec<Id>,
+    selected: usize,
+    /// How far the selected card is scrolled.
+    scroll: Scroll,
+}
 
-pub fn view_cards(stdout: &mut Stdout, mut cards: Vec<Id>, cache: &mut CardCache) {
-    if cards.is_empty() {
-        draw_message(stdout, "No cards found");
-        return;
+impl ViewCardsPage {
+    pub fn new(cards: Vec<Id>) -> Self {
+        Self {
+            cards,
+            selected: 0,
+            scroll: Scroll::default(),
+        }
     }
 
-    let mut selected = 0;
+    pub fn all_cards(cache: &mut CardCache) -> Self {
+        Self::new(cache.all_ids())
+    }
 
-    loop {
-        let card_qty = cards.len();
-        let card = cache.get_ref(&cards[selected]);
-        let mut excluded_cards = HashSet::new();
-        excluded_cards.insert(card.id().to_owned());
+    /// Moves the given card to the front of the list and selects it.
+    fn jump_to(&mut self, stdout: &mut Stdout, card: &Id) {
+        match self.cards.iter().position(|id| id == card) {
+            Some(idx) => {
+                self.cards.swap(0, idx);
+                self.selected = 0;
+                self.scroll = Scroll::default();
+            }
+            None => {
+                draw_warning(stdout, "damn ...");
+            }
+        }
+    }
 
-        let message = format!(
-            "{}/{}\t{}\n{}\n=============================\n{}",
-            selected + 1,
-            card_qty,
+    /// Removes the selected card from the list, closing the page if it was the last one.
+    fn remove_selected(&mut self, stdout: &mut Stdout) -> PageAction {
+        self.cards.remove(self.selected);
+        if self.cards.is_empty() {
+            draw_message(stdout, "No more cards");
+            return PageAction::Pop;
+        }
+        if self.selected == self.cards.len() {
+            self.selected -= 1;
+        }
+        PageAction::None
+    }
+}
+
+impl Page for ViewCardsPage {
+    fn render(&mut self, stdout: &mut Stdout, cache: &mut CardCache) {
+        clear_window(stdout);
+        move_upper_left(stdout);
+
+        if self.cards.is_empty() {
+            write_string(stdout, "No cards found");
+            return;
+        }
+
+        let card = cache.get_ref(&self.cards[self.selected]);
+        let header = format!(
+            "{}/{}\t{}\n",
+            self.selected + 1,
+            self.cards.len(),
             card.category().print_full(),
-            card.front_text(),
-            card.back_text()
         );
 
-        let key_event = draw_key_event_message(stdout, &message);
+        write_string(stdout, &header);
+        card_layout(card.card_as_ref(), true, vec![], 1).draw(stdout, self.scroll);
+    }
+
+    fn handle_key(
+        &mut self,
+        stdout: &mut Stdout,
+        key: KeyEvent,
+        cache: &mut CardCache,
+    ) -> PageAction {
+        if self.cards.is_empty() {
+            return PageAction::Pop;
+        }
+
+        let card_qty = self.cards.len();
+        let card = cache.get_ref(&self.cards[self.selected]);
+        let mut excluded_cards = HashSet::new();
+        excluded_cards.insert(card.id().to_owned());
+
+        let Some(action) = keymap().action(Scope::Browse, &key) else {
+            return PageAction::None;
+        };
+
+        if edit_card(stdout, action, card.clone(), cache) {
+            return PageAction::None;
+        }
 
-        if edit_card(stdout, &key_event.code, card.clone(), cache) {
-            continue;
+        let layout = card_layout(card.card_as_ref(), true, vec![], 1);
+        if let Some(scroll) = scroll_card(action, self.scroll, &layout) {
+            self.scroll = scroll;
+            return PageAction::None;
         }
 
-        match key_event.code {
-            KeyCode::Char('l') | KeyCode::Right if selected != card_qty - 1 => selected += 1,
-            KeyCode::Char('h') | KeyCode::Left if selected != 0 => selected -= 1,
-            KeyCode::Char('.') => panic!(),
-            KeyCode::Char('X') => {
+        match action {
+            Action::NextCard | Action::Skip if self.selected != card_qty - 1 => {
+                self.selected += 1;
+                self.scroll = Scroll::default();
+            }
+            Action::PrevCard if self.selected != 0 => {
+                self.selected -= 1;
+                self.scroll = Scroll::default();
+            }
+            Action::DependencyTree => {
                 if let Some(thecard) = ascii_test(stdout, card.id(), cache, true) {
-                    let mut idx = None;
-                    for card in cards.iter().enumerate() {
-                        if card.1 == thecard.id() {
-                            idx = Some(card.0);
-                        }
-                    }
-
-                    if let Some(idx) = idx {
-                        cards.swap(0, idx);
-                        selected = 0;
-                    } else {
-                        draw_message(stdout, "damn ...");
-                    }
+                    self.jump_to(stdout, thecard.id());
                 }
             }
 
-            KeyCode::Char('x') => {
+            Action::DependentTree => {
                 if let Some(thecard) = ascii_test(stdout, card.id(), cache, false) {
-                    let mut idx = None;
-                    for card in cards.iter().enumerate() {
-                        if card.1 == thecard.id() {
-                            idx = Some(card.0);
-                        }
-                    }
-
-                    if let Some(idx) = idx {
-                        cards.swap(0, idx);
-                        selected = 0;
-                    } else {
-                        draw_message(stdout, "damn ...");
-                    }
+                    self.jump_to(stdout, thecard.id());
                 }
             }
 
-            KeyCode::Char('T') => {
+            Action::NewDependent => {
                 draw_message(stdout, "Adding new dependent");
                 let category = Some(card.category().to_owned());
-                if let Some(updated_card) =
-                    add_dependent(stdout, card.id(), category.as_ref(), cache)
-                {
-                    cards.insert(0, *updated_card.id());
+                for new_card in add_dependent(stdout, card.id(), category.as_ref(), cache) {
+                    self.cards.insert(0, *new_card.id());
                 }
             }
-            KeyCode::Char('Y') => {
+            Action::NewDependency => {
                 draw_message(stdout, "Adding new dependency");
                 let category = Some(card.category().to_owned());
-                if let Some(updated_card) =
-                    add_dependency(stdout, card.id(), category.as_ref(), cache)
-                {
-                    cards.insert(0, *updated_card.id());
+                for new_card in add_dependency(stdout, card.id(), category.as_ref(), cache) {
+                    self.cards.insert(0, *new_card.id());
                 }
             }
-            KeyCode::Char('i') => {
+            Action::CopyCard => {
                 let front_text = card.front_text().to_owned();
                 let back_text = card.back_text().to_owned();
-                if let Some(card) = add_card(
+                for card in add_card(
+                    stdout,
                     Some((front_text, back_text)),
                     &mut card.category().clone(),
                     cache,
                 ) {
-                    cards.insert(0, card.id().to_owned()); // temp thing
+                    self.cards.insert(0, card.id().to_owned()); // temp thing
                 }
             }
 
-            KeyCode::Char('a') => {
-                if let Some(card) = add_card(None, &mut card.category().clone(), cache) {
-                    cards.insert(0, card.id().to_owned()); // temp thing
+            Action::AddCard => {
+                for card in add_card(stdout, None, &mut card.category().clone(), cache) {
+                    self.cards.insert(0, card.id().to_owned()); // temp thing
                 }
             }
-            KeyCode::Char('A') => {
-                if let Some(card) = add_card(None, &mut card.category().clone(), cache) {
-                    cards.insert(0, card.id().to_owned()); // temp thing
+            Action::AddCardWithAi => {
+                let cards = add_card(stdout, None, &mut card.category().clone(), cache);
+                if let Some(card) = cards.into_iter().next() {
+                    self.cards.insert(0, card.id().to_owned()); // temp thing
                     let card = Arc::new(card);
                     tokio::runtime::Runtime::new().unwrap().block_on(async {
                         fix_question(card.clone(), cache).await;
@@ -133,55 +185,32 @@ pub fn view_cards(stdout: &mut Stdout, mut cards: Vec<Id>, cache: &mut CardCache
                     })
                 }
             }
-            KeyCode::Char('r') => {
-                cards.remove(selected);
-                if cards.is_empty() {
-                    draw_message(stdout, "No more cards");
-                    return;
-                }
-                if selected == cards.len() {
-                    selected -= 1;
-                }
+            Action::RemoveFromList => return self.remove_selected(stdout),
+            Action::Delete if affirmative(stdout, "Delete card?") => {
+                let change = Change::new("delete").with_neighbours(card.id(), cache);
+                cache.get_owned(card.id()).delete(cache);
+                change.record();
+                draw_message(stdout, "Card deleted");
+                return self.remove_selected(stdout);
             }
-            KeyCode::Char('D') => {
-                if affirmative(stdout, "Delete card?") {
-                    cache.get_owned(card.id()).delete(cache);
-                    draw_message(stdout, "Card deleted");
-                    cards.remove(selected);
-                    if cards.is_empty() {
-                        draw_message(stdout, "No more cards");
-                        return;
-                    }
-                    if selected == cards.len() {
-                        selected -= 1;
-                    }
-                }
-            }
-            KeyCode::Char('s') => {}
-            KeyCode::Char('/') => {
+            Action::Search => {
                 if let Some(thecard) = search_for_item(stdout, "find some card", excluded_cards) {
-                    let mut idx = None;
-                    for card in cards.iter().enumerate() {
-                        if card.1 == thecard.id() {
-                            idx = Some(card.0);
-                        }
-                    }
-
-                    if let Some(idx) = idx {
-                        cards.swap(0, idx);
-                        selected = 0;
-                    } else {
-                        draw_message(stdout, "damn ...");
-                    }
+                    self.jump_to(stdout, thecard.id());
                 }
             }
-            key if should_exit(&key) => return,
+            Action::Undo => {
+                undo_last(stdout, cache);
+                self.on_resume(stdout, cache);
+            }
+            Action::Help => return PageAction::Push(Box::new(HelpPage::new(Scope::Browse))),
+            Action::Quit => return PageAction::Pop,
             _ => {}
         };
+        PageAction::None
     }
-}
 
-pub fn view_all_cards(stdout: &mut Stdout, cache: &mut CardCache) {
-    let cards = cache.all_ids();
-    view_cards(stdout, cards, cache);
+    fn on_resume(&mut self, _stdout: &mut Stdout, cache: &mut CardCache) {
+        self.cards.retain(|card| cache.exists(card));
+        self.selected = self.selected.min(self.cards.len().saturating_sub(1));
+    }
 }
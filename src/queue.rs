@@ -0,0 +1,179 @@
+//! The orders a review queue can be put in.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use speki_backend::card::{CardCache, SavedCard};
+use speki_backend::Id;
+
+/// How long a card is assumed to take when it has no reviews to go by.
+const DEFAULT_REVIEW_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Order {
+    /// Highest expected gain first.
+    #[default]
+    Gain,
+    Random,
+    /// Highest expected gain per second the card is expected to take first.
+    GainPerSecond,
+    /// By gain, but spread evenly over the categories and keeping related cards apart.
+    Interleaved,
+    /// By gain, but with dependencies before the cards that depend on them.
+    Dependencies,
+}
+
+/// The average time the card took in its earlier reviews.
+fn average_review_time(card: &SavedCard) -> Option<Duration> {
+    let times: Vec<Duration> = card
+        .reviews()
+        .iter()
+        .map(|review| review.time_spent)
+        .filter(|time| !time.is_zero())
+        .collect();
+    let total: Duration = times.iter().sum();
+    total.checked_div(times.len() as u32)
+}
+
+fn by_gain(cards: &mut [Id], cache: &mut CardCache) {
+    cards.sort_by_key(|card| {
+        (cache.get_ref(card).expected_gain().unwrap_or_default() * 1000.) as i32
+    });
+    cards.reverse();
+}
+
+pub fn sort(mut cards: Vec<Id>, order: Order, cache: &mut CardCache) -> Vec<Id> {
+    match order {
+        Order::Random => {
+            let mut rng = rand::thread_rng();
+            cards.shuffle(&mut rng);
+        }
+        Order::Gain => by_gain(&mut cards, cache),
+        Order::GainPerSecond => {
+            let mut scored: Vec<(f32, Id)> = cards
+                .iter()
+                .map(|id| {
+                    let card = cache.get_ref(id);
+                    let time = average_review_time(&card).unwrap_or(DEFAULT_REVIEW_TIME);
+                    let gain = card.expected_gain().unwrap_or_default();
+                    (gain / time.as_secs_f32(), *id)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            cards = scored.into_iter().map(|(_, id)| id).collect();
+        }
+        Order::Interleaved => {
+            by_gain(&mut cards, cache);
+            cards = interleave(cards, cache);
+        }
+        Order::Dependencies => {
+            by_gain(&mut cards, cache);
+            cards = dependencies_first(cards, cache);
+        }
+    }
+    cards
+}
+
+/// Whether one of the cards is a direct dependency of the other.
+fn related(a: &Id, b: &Id, cache: &mut CardCache) -> bool {
+    cache.dependencies(a).contains(b) || cache.dependencies(b).contains(a)
+}
+
+/// Spreads the cards of each category evenly over the queue, keeping their order within it.
+///
+/// Every step takes from the category that's furthest behind its share of the queue,
+/// unless its next card is related to the card before, then the next furthest behind is tried.
+fn interleave(cards: Vec<Id>, cache: &mut CardCache) -> Vec<Id> {
+    let mut categories: BTreeMap<String, Vec<Id>> = BTreeMap::new();
+    for card in cards {
+        let category = cache.get_ref(&card).category().print_full();
+        categories.entry(category).or_default().push(card);
+    }
+
+    // Back to front so the next card can be popped off, along with how many there were.
+    let mut queues: Vec<(Vec<Id>, usize)> = categories
+        .into_values()
+        .map(|mut cards| {
+            cards.reverse();
+            let len = cards.len();
+            (cards, len)
+        })
+        .collect();
+
+    let total: usize = queues.iter().map(|(_, len)| len).sum();
+    let mut interleaved: Vec<Id> = Vec::with_capacity(total);
+
+    while interleaved.len() < total {
+        // How far along each category would be after taking one more card from it.
+        let mut candidates: Vec<(f32, usize)> = queues
+            .iter()
+            .enumerate()
+            .filter(|(_, (queue, _))| !queue.is_empty())
+            .map(|(idx, (queue, len))| ((len - queue.len() + 1) as f32 / *len as f32, idx))
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let chosen = match interleaved.last() {
+            Some(prev) => candidates
+                .iter()
+                .map(|(_, idx)| *idx)
+                .find(|idx| !related(prev, queues[*idx].0.last().unwrap(), cache)),
+            None => None,
+        }
+        .unwrap_or(candidates[0].1);
+
+        interleaved.push(queues[chosen].0.pop().unwrap());
+    }
+
+    interleaved
+}
+
+/// Puts every card after its dependencies in the queue, keeping the order otherwise.
+///
+/// Dependencies that aren't in the queue themselves don't count, but the ones behind them do.
+/// Cards in a dependency cycle go by the order they came in.
+fn dependencies_first(cards: Vec<Id>, cache: &mut CardCache) -> Vec<Id> {
+    let index: HashMap<Id, usize> = cards
+        .iter()
+        .enumerate()
+        .map(|(idx, card)| (*card, idx))
+        .collect();
+
+    // How many queued dependencies each card waits on, and which cards wait on each card.
+    let mut waiting = vec![0; cards.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![vec![]; cards.len()];
+    for (idx, card) in cards.iter().enumerate() {
+        for dependency in cache.recursive_dependencies(card) {
+            if let Some(dependency) = index.get(&dependency) {
+                waiting[idx] += 1;
+                dependents[*dependency].push(idx);
+            }
+        }
+    }
+
+    // Indices into `cards`, so the first in the set is the first that came in.
+    let mut ready: BTreeSet<usize> = (0..cards.len()).filter(|idx| waiting[*idx] == 0).collect();
+    let mut unplaced: BTreeSet<usize> = (0..cards.len()).collect();
+    let mut sorted = Vec::with_capacity(cards.len());
+
+    while let Some(first) = unplaced.first().copied() {
+        // Nothing is ready when the rest are in cycles.
+        let next = ready.pop_first().unwrap_or(first);
+        unplaced.remove(&next);
+        sorted.push(cards[next]);
+
+        for dependent in &dependents[next] {
+            waiting[*dependent] -= 1;
+            if waiting[*dependent] == 0 && unplaced.contains(dependent) {
+                ready.insert(*dependent);
+            }
+        }
+    }
+
+    sorted
+}
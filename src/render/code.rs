@@ -0,0 +1,355 @@
+//! Highlighting for the lines of fenced code blocks.
+//!
+//! Rust and SQL get their own keywords, any other language a common set of them. Block comments
+//! and the strings that can span lines carry over from one line of a block to the next.
+
+use crossterm::style::ContentStyle;
+
+use super::Line;
+use crate::theme::theme;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Language {
+    Rust,
+    Sql,
+    Other,
+}
+
+impl Language {
+    /// From the info string after the opening fence, going by the word before any attributes like
+    /// the `ignore` in `rust,ignore`.
+    fn from_fence(info: &str) -> Self {
+        let name = info
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        match name.to_lowercase().as_str() {
+            "rust" | "rs" => Self::Rust,
+            "sql" | "postgres" | "postgresql" | "mysql" | "sqlite" => Self::Sql,
+            _ => Self::Other,
+        }
+    }
+
+    fn is_keyword(self, word: &str) -> bool {
+        match self {
+            Self::Rust => RUST_KEYWORDS.contains(&word),
+            Self::Sql => SQL_KEYWORDS.contains(&word.to_uppercase().as_str()),
+            Self::Other => OTHER_KEYWORDS.contains(&word),
+        }
+    }
+
+    fn is_comment(self, rest: &[char]) -> bool {
+        let starts_with = |prefix: &str| rest.iter().copied().take(prefix.len()).eq(prefix.chars());
+        match self {
+            Self::Rust => starts_with("//"),
+            Self::Sql => starts_with("--"),
+            Self::Other => starts_with("//") || starts_with("#"),
+        }
+    }
+
+    fn is_quote(self, c: char) -> bool {
+        match self {
+            Self::Rust => c == '"',
+            Self::Sql | Self::Other => c == '"' || c == '\'' || c == '`',
+        }
+    }
+
+    /// Whether a string started with `quote` goes on past the end of the line when it isn't closed.
+    fn spans_lines(self, quote: char) -> bool {
+        match self {
+            Self::Rust => quote == '"',
+            Self::Sql => quote == '\'',
+            Self::Other => quote == '`',
+        }
+    }
+
+    fn nests_comments(self) -> bool {
+        self == Self::Rust
+    }
+}
+
+/// What the last line ended inside of.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Open {
+    /// A string started with the quote.
+    String(char),
+    /// A block comment, nested this deep.
+    Comment(usize),
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const SQL_KEYWORDS: &[&str] = &[
+    "ADD",
+    "ALL",
+    "ALTER",
+    "AND",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CREATE",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "EXISTS",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NOT",
+    "NULL",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "PRIMARY",
+    "REFERENCES",
+    "RIGHT",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "THEN",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "VALUES",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+const OTHER_KEYWORDS: &[&str] = &[
+    "break", "case", "class", "const", "continue", "def", "do", "else", "elif", "false", "False",
+    "fn", "for", "func", "function", "if", "import", "in", "let", "new", "nil", "null", "None",
+    "return", "static", "struct", "switch", "this", "true", "True", "var", "while",
+];
+
+/// Highlights the lines of one code block, in order.
+pub struct Highlighter {
+    language: Language,
+    open: Option<Open>,
+}
+
+impl Highlighter {
+    /// For a block with `info` after its opening fence.
+    pub fn new(info: &str) -> Self {
+        Self {
+            language: Language::from_fence(info),
+            open: None,
+        }
+    }
+
+    pub fn line(&mut self, text: &str) -> Line {
+        let language = self.language;
+        let theme = theme();
+        let chars: Vec<char> = text.replace('\t', "    ").chars().collect();
+        let mut line = Line {
+            code: true,
+            ..Line::default()
+        };
+
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let mut idx = 0;
+        while idx < chars.len() {
+            let c = chars[idx];
+            let rest = &chars[idx..];
+
+            let (len, style) = if let Some(Open::String(quote)) = self.open {
+                let (len, closed) = string_len(rest, quote);
+                if closed {
+                    self.open = None;
+                }
+                (len, theme.string)
+            } else if let Some(Open::Comment(depth)) = self.open {
+                let (len, depth) = comment_len(rest, depth, language.nests_comments());
+                self.open = (depth > 0).then_some(Open::Comment(depth));
+                (len, theme.comment)
+            } else if language.is_comment(rest) {
+                (rest.len(), theme.comment)
+            } else if rest.starts_with(&['/', '*']) {
+                self.open = Some(Open::Comment(1));
+                (2, theme.comment)
+            } else if language.is_quote(c) {
+                self.open = Some(Open::String(c));
+                (1, theme.string)
+            } else if language == Language::Rust && c == '\'' && is_char_literal(rest) {
+                (char_literal_len(rest), theme.string)
+            } else if c.is_ascii_digit() {
+                let len = rest
+                    .iter()
+                    .take_while(|c| is_ident(**c) || **c == '.')
+                    .count();
+                (len, theme.number)
+            } else if is_ident(c) {
+                let len = rest.iter().take_while(|c| is_ident(**c)).count();
+                let word: String = rest[..len].iter().collect();
+                let style = if language.is_keyword(&word) {
+                    theme.keyword
+                } else if language == Language::Rust && rest.get(len) == Some(&'!') {
+                    theme.macro_name
+                } else if language == Language::Rust && c.is_uppercase() {
+                    theme.type_name
+                } else {
+                    ContentStyle::new()
+                };
+                (len, style)
+            } else {
+                (1, ContentStyle::new())
+            };
+
+            for c in &rest[..len] {
+                line.push(*c, style);
+            }
+            idx += len;
+        }
+
+        if let Some(Open::String(quote)) = self.open {
+            if !language.spans_lines(quote) {
+                self.open = None;
+            }
+        }
+
+        line
+    }
+}
+
+/// How far the string goes, up to and with its closing quote, and whether it was closed.
+fn string_len(rest: &[char], quote: char) -> (usize, bool) {
+    let mut len = 0;
+    while len < rest.len() {
+        match rest[len] {
+            '\\' => len += 2,
+            c if c == quote => return (len + 1, true),
+            _ => len += 1,
+        }
+    }
+    (rest.len(), false)
+}
+
+/// How far the comment goes, up to and with its closing `*/`, and how deep it's still nested.
+fn comment_len(rest: &[char], mut depth: usize, nests: bool) -> (usize, usize) {
+    let mut len = 0;
+    while len < rest.len() {
+        if rest[len..].starts_with(&['*', '/']) {
+            depth -= 1;
+            len += 2;
+            if depth == 0 {
+                return (len, 0);
+            }
+        } else if nests && rest[len..].starts_with(&['/', '*']) {
+            depth += 1;
+            len += 2;
+        } else {
+            len += 1;
+        }
+    }
+    (rest.len(), depth)
+}
+
+/// `'a'` or `'\n'`, rather than a lifetime like `'a`.
+fn is_char_literal(rest: &[char]) -> bool {
+    match rest.get(1) {
+        Some('\\') => rest.iter().skip(3).take(9).any(|c| *c == '\''),
+        Some(_) => rest.get(2) == Some(&'\''),
+        None => false,
+    }
+}
+
+fn char_literal_len(rest: &[char]) -> usize {
+    match rest[1] {
+        '\\' => rest[3..].iter().position(|c| *c == '\'').unwrap() + 4,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The style of each token, going by its first char.
+    fn style_of(line: &Line, token: &str) -> ContentStyle {
+        let text: String = line.spans.iter().map(|span| span.text.as_str()).collect();
+        let start = text.find(token).unwrap();
+        let start = text[..start].chars().count();
+        let mut seen = 0;
+        for span in &line.spans {
+            seen += span.text.chars().count();
+            if seen > start {
+                return span.style;
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn reads_the_language_before_attributes() {
+        assert!(Language::from_fence("rust,ignore") == Language::Rust);
+        assert!(Language::from_fence("rust no_run") == Language::Rust);
+        assert!(Language::from_fence("SQL") == Language::Sql);
+        assert!(Language::from_fence("") == Language::Other);
+    }
+
+    #[test]
+    fn block_comments_go_on_until_closed() {
+        let mut highlighter = Highlighter::new("rust");
+        let first = highlighter.line("let a = 1; /* starts");
+        let middle = highlighter.line("fn still a comment");
+        let last = highlighter.line("ends */ let b = 2;");
+
+        assert_eq!(style_of(&first, "let"), theme().keyword);
+        assert_eq!(style_of(&first, "/*"), theme().comment);
+        assert_eq!(style_of(&middle, "fn"), theme().comment);
+        assert_eq!(style_of(&last, "ends"), theme().comment);
+        assert_eq!(style_of(&last, "let"), theme().keyword);
+    }
+
+    #[test]
+    fn rust_block_comments_nest() {
+        let mut highlighter = Highlighter::new("rust");
+        highlighter.line("/* outer /* inner */");
+        let line = highlighter.line("fn */ fn");
+        assert_eq!(style_of(&line, "fn"), theme().comment);
+        assert_eq!(style_of(&line, " fn"), ContentStyle::new());
+        assert_eq!(highlighter.open, None);
+    }
+
+    #[test]
+    fn strings_go_on_until_closed() {
+        let mut highlighter = Highlighter::new("rust");
+        highlighter.line(r#"let s = "first"#);
+        let line = highlighter.line(r#"fn \" still" fn"#);
+        assert_eq!(style_of(&line, "fn"), theme().string);
+        assert_eq!(style_of(&line, "\" fn"), theme().string);
+        assert_eq!(highlighter.open, None);
+    }
+
+    #[test]
+    fn only_some_strings_span_lines() {
+        let mut highlighter = Highlighter::new("python");
+        highlighter.line("it's");
+        let line = highlighter.line("if x");
+        assert_eq!(style_of(&line, "if"), theme().keyword);
+    }
+}
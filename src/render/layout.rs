@@ -0,0 +1,118 @@
+//! Fits a card on the screen. The front stays at the top and the back scrolls under it when
+//! the two don't fit together.
+
+use std::io::{Stdout, Write};
+
+use crossterm::style::{Attribute, ContentStyle};
+use crossterm::terminal;
+
+use crate::theme::theme;
+
+use super::{max_scroll, print_rows, terminal_width, Line, Span};
+
+/// How far a card is scrolled, down through the back and sideways through its code.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Scroll {
+    pub row: usize,
+    pub column: usize,
+}
+
+const SEPARATOR: &str = "===================================================";
+
+pub struct CardLayout {
+    front: Vec<Line>,
+    /// `None` while the back is hidden.
+    back: Option<Vec<Line>>,
+    /// The first row on screen that's ours.
+    top: u16,
+}
+
+/// Rows given to the front, the back and whether a row is kept for the scroll hint.
+struct Regions {
+    front: usize,
+    back: usize,
+    hint: bool,
+}
+
+impl CardLayout {
+    pub fn new(front: Vec<Line>, back: Option<Vec<Line>>, top: u16) -> Self {
+        Self { front, back, top }
+    }
+
+    fn regions(&self) -> Regions {
+        let (_, rows) = terminal::size().unwrap();
+        let available = rows.saturating_sub(self.top) as usize;
+
+        let Some(back) = &self.back else {
+            return Regions {
+                front: self.front.len().min(available),
+                back: 0,
+                hint: false,
+            };
+        };
+
+        // The separator takes a row.
+        if self.front.len() + 1 + back.len() <= available {
+            return Regions {
+                front: self.front.len(),
+                back: back.len(),
+                hint: false,
+            };
+        }
+
+        // The front gets up to half the screen, the back what's left but the separator and hint.
+        let front = self.front.len().min(available / 2);
+        Regions {
+            front,
+            back: available.saturating_sub(front + 2),
+            hint: true,
+        }
+    }
+
+    /// How far down the back can scroll.
+    pub fn max_row(&self) -> usize {
+        let back = self.back.as_ref().map(Vec::len).unwrap_or_default();
+        back.saturating_sub(self.regions().back)
+    }
+
+    /// How far sideways the code blocks can scroll.
+    pub fn max_column(&self) -> usize {
+        let back = self.back.iter().flatten();
+        let lines: Vec<Line> = self.front.iter().chain(back).cloned().collect();
+        max_scroll(&lines, terminal_width())
+    }
+
+    /// Draws the card from the top row down, scrolled as far as `scroll` but no further.
+    pub fn draw(&self, stdout: &mut Stdout, scroll: Scroll) {
+        let regions = self.regions();
+        let column = scroll.column.min(self.max_column());
+        let mut front: Vec<Line> = self.front.iter().take(regions.front).cloned().collect();
+        if regions.front < self.front.len() {
+            if let Some(last) = front.last_mut() {
+                *last = hint("…");
+            }
+        }
+        print_rows(stdout, self.top, &front, column);
+
+        let Some(back) = &self.back else {
+            stdout.flush().unwrap();
+            return;
+        };
+
+        let row = scroll.row.min(self.max_row());
+        let mut lines = vec![Line::new(vec![Span::new(SEPARATOR, theme().separator)])];
+        lines.extend(back.iter().skip(row).take(regions.back).cloned());
+        if regions.hint {
+            let below = back.len().saturating_sub(row + regions.back);
+            lines.push(hint(&format!("{} lines above, {} below", row, below)));
+        }
+        print_rows(stdout, self.top + regions.front as u16, &lines, column);
+        stdout.flush().unwrap();
+    }
+}
+
+fn hint(text: &str) -> Line {
+    let mut style = ContentStyle::new();
+    style.attributes.set(Attribute::Dim);
+    Line::new(vec![Span::new(text, style)])
+}
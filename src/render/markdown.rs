@@ -0,0 +1,415 @@
+//! The markdown cards are written in: headings, lists, block quotes, rules, and `**bold**`,
+//! `*italic*`, `~~struck~~` and `` `code` `` inline.
+//!
+//! Line breaks are kept as they are, flashcards are short and laid out by hand. Fenced code
+//! blocks are highlighted by [`super::code`] and math is drawn by [`super::math`].
+
+use crossterm::style::{Attribute, ContentStyle};
+
+use super::{code, math, wrap, Line, Span};
+use crate::theme::theme;
+
+pub fn render(text: &str, width: usize) -> Vec<Line> {
+    let mut lines = vec![];
+    // The highlighter of the code block we're in, if any.
+    let mut fence: Option<code::Highlighter> = None;
+    // The lines of the `$$` block we're in, if any.
+    let mut formula: Option<Vec<&str>> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(lines_so_far) = &mut formula {
+            match trimmed.strip_suffix("$$") {
+                Some(last) => {
+                    lines_so_far.push(last);
+                    lines.extend(display_math(&lines_so_far.join("\n")));
+                    formula = None;
+                }
+                None => lines_so_far.push(line),
+            }
+            continue;
+        }
+
+        if fence.is_none() {
+            if let Some(first) = trimmed.strip_prefix("$$") {
+                match first.strip_suffix("$$") {
+                    Some(whole) => lines.extend(display_math(whole)),
+                    None => formula = Some(vec![first]),
+                }
+                continue;
+            }
+        }
+
+        if let Some(info) = line.trim_start().strip_prefix("```") {
+            fence = match fence {
+                Some(_) => None,
+                None => Some(code::Highlighter::new(info.trim())),
+            };
+            continue;
+        }
+
+        match &mut fence {
+            Some(highlighter) => lines.push(highlighter.line(line)),
+            None => lines.extend(block(line, width)),
+        }
+    }
+
+    // A block that was never closed.
+    if let Some(lines_so_far) = formula {
+        lines.extend(display_math(&lines_so_far.join("\n")));
+    }
+
+    lines
+}
+
+/// Display math doesn't wrap, it scrolls sideways like code does.
+fn display_math(text: &str) -> Vec<Line> {
+    math::display(text)
+        .into_iter()
+        .map(|row| Line {
+            code: true,
+            ..Line::new(vec![Span::plain(format!("  {}", row))])
+        })
+        .collect()
+}
+
+fn with(style: ContentStyle, attribute: Attribute) -> ContentStyle {
+    let mut style = style;
+    style.attributes.set(attribute);
+    style
+}
+
+fn dim(text: &str) -> Span {
+    Span::new(text, with(ContentStyle::new(), Attribute::Dim))
+}
+
+/// `---`, `***` or `___`, spaces allowed in between.
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|c| chars.iter().all(|x| x == c))
+}
+
+/// The `#`s of a heading and the text after them.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text))
+}
+
+/// The marker of a list item, `•` for bullets and the number for ordered lists, and its text.
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some(("• ".to_string(), text));
+        }
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    let rest = &line[digits..];
+    let text = rest
+        .strip_prefix(". ")
+        .or_else(|| rest.strip_prefix(") "))?;
+    Some((format!("{}. ", &line[..digits]), text))
+}
+
+fn block(line: &str, width: usize) -> Vec<Line> {
+    let trimmed = line.trim_start();
+    let indent = " ".repeat(line.chars().count() - trimmed.chars().count());
+
+    if trimmed.is_empty() {
+        return vec![Line::default()];
+    }
+
+    if is_rule(trimmed) {
+        return vec![Line::new(vec![dim(&"─".repeat(width))])];
+    }
+
+    if let Some((level, text)) = heading(trimmed) {
+        let mut style = with(ContentStyle::new(), Attribute::Bold);
+        if level == 1 {
+            style.attributes.set(Attribute::Underlined);
+        }
+        return wrap(inline(text, style), width, Span::plain(""), Span::plain(""));
+    }
+
+    if let Some(quoted) = trimmed.strip_prefix('>') {
+        let quoted = quoted.strip_prefix(' ').unwrap_or(quoted);
+        let prefix = format!("{}│ ", indent);
+        let width = width.saturating_sub(prefix.chars().count());
+        return block(quoted, width)
+            .into_iter()
+            .map(|mut line| {
+                line.spans.insert(0, dim(&prefix));
+                line
+            })
+            .collect();
+    }
+
+    if let Some((marker, text)) = list_item(trimmed) {
+        let first = Span::plain(format!("{}{}", indent, marker));
+        let rest = Span::plain(" ".repeat(first.text.chars().count()));
+        return wrap(inline(text, ContentStyle::new()), width, first, rest);
+    }
+
+    let first = Span::plain(indent);
+    let rest = first.clone();
+    wrap(inline(trimmed, ContentStyle::new()), width, first, rest)
+}
+
+/// Splits the text into spans by its inline markup, on top of the `base` style.
+fn inline(text: &str, base: ContentStyle) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut line = Line::default();
+    let (mut bold, mut italic, mut struck) = (false, false, false);
+
+    let style = |bold: bool, italic: bool, struck: bool| {
+        let mut style = base;
+        if bold {
+            style.attributes.set(Attribute::Bold);
+        }
+        if italic {
+            style.attributes.set(Attribute::Italic);
+        }
+        if struck {
+            style.attributes.set(Attribute::CrossedOut);
+        }
+        style
+    };
+    // Markup only opens if it's closed again later on.
+    let closes = |from: usize, delimiter: &[char]| {
+        chars[from..]
+            .windows(delimiter.len())
+            .any(|window| window == delimiter)
+    };
+    let is_word = |idx: Option<usize>| {
+        idx.and_then(|idx| chars.get(idx))
+            .is_some_and(|c| c.is_alphanumeric())
+    };
+    // Like `2 * 3`, markup can't open before a space or close after one.
+    let toggles = |open: bool, idx: usize, len: usize| {
+        if open {
+            chars.get(idx + len).is_some_and(|c| !c.is_whitespace())
+                && closes(idx + len, &chars[idx..idx + len])
+        } else {
+            idx.checked_sub(1)
+                .and_then(|idx| chars.get(idx))
+                .is_some_and(|c| !c.is_whitespace())
+        }
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        let next = chars.get(idx + 1).copied();
+
+        if c == '\\' && next.is_some_and(|next| next.is_ascii_punctuation()) {
+            line.push(next.unwrap(), style(bold, italic, struck));
+            idx += 2;
+            continue;
+        }
+
+        if c == '`' {
+            if let Some(len) = chars[idx + 1..].iter().position(|c| *c == '`') {
+                let mut code = base;
+                code.foreground_color = theme().code.foreground_color;
+                code.attributes.extend(theme().code.attributes);
+                for c in &chars[idx + 1..idx + 1 + len] {
+                    line.push(*c, code);
+                }
+                idx += len + 2;
+                continue;
+            }
+        }
+
+        if let Some(len) = inline_math(&chars[idx..]) {
+            let delimiter = if next == Some('$') { 2 } else { 1 };
+            let formula: String = chars[idx + delimiter..idx + len - delimiter]
+                .iter()
+                .collect();
+            for c in math::inline(&formula).chars() {
+                line.push(c, style(bold, italic, struck));
+            }
+            idx += len;
+            continue;
+        }
+
+        if (c == '*' || c == '_') && next == Some(c) && toggles(!bold, idx, 2) {
+            bold = !bold;
+            idx += 2;
+            continue;
+        }
+
+        if c == '~' && next == Some('~') && toggles(!struck, idx, 2) {
+            struck = !struck;
+            idx += 2;
+            continue;
+        }
+
+        // Underscores inside words, like in snake_case, stay as they are.
+        let in_word = c == '_' && (is_word(idx.checked_sub(1)) && is_word(Some(idx + 1)));
+        if (c == '*' || c == '_') && !in_word && toggles(!italic, idx, 1) {
+            italic = !italic;
+            idx += 1;
+            continue;
+        }
+
+        line.push(c, style(bold, italic, struck));
+        idx += 1;
+    }
+
+    line.spans
+}
+
+/// The length of the `$...$` or `$$...$$` at the start of `chars`, delimiters included.
+///
+/// Like in pandoc, the math can't start with a space or end with one, and the closing `$` can't
+/// be followed by a digit, so prices like `$5 and $10` are left alone.
+fn inline_math(chars: &[char]) -> Option<usize> {
+    let delimiter = match chars {
+        ['$', '$', ..] => 2,
+        ['$', ..] => 1,
+        _ => return None,
+    };
+    match chars.get(delimiter) {
+        Some(c) if !c.is_whitespace() => {}
+        _ => return None,
+    }
+
+    (delimiter + 1..chars.len()).find_map(|end| {
+        let closes = chars[end..].iter().take(delimiter).all(|c| *c == '$')
+            && chars.len() >= end + delimiter
+            && !chars[end - 1].is_whitespace()
+            && chars[end - 1] != '\\'
+            && !chars.get(end + delimiter).is_some_and(char::is_ascii_digit);
+        closes.then_some(end + delimiter)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn math_len(text: &str) -> Option<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        inline_math(&chars)
+    }
+
+    /// The text of each span, with whether it's bold, italic and struck.
+    fn spans(text: &str) -> Vec<(String, bool, bool, bool)> {
+        inline(text, ContentStyle::new())
+            .into_iter()
+            .map(|span| {
+                let has = |attribute| span.style.attributes.has(attribute);
+                (
+                    span.text.clone(),
+                    has(Attribute::Bold),
+                    has(Attribute::Italic),
+                    has(Attribute::CrossedOut),
+                )
+            })
+            .collect()
+    }
+
+    fn plain(text: &str) -> (String, bool, bool, bool) {
+        (text.to_string(), false, false, false)
+    }
+
+    #[test]
+    fn inline_math_needs_closing() {
+        assert_eq!(math_len("$x^2$ and more"), Some(5));
+        assert_eq!(math_len("$$x$$"), Some(5));
+        assert_eq!(math_len("$x"), None);
+        assert_eq!(math_len("no math"), None);
+    }
+
+    #[test]
+    fn prices_arent_math() {
+        assert_eq!(math_len("$5 and $10"), None);
+        assert_eq!(math_len("$ x$"), None);
+        assert_eq!(math_len("$x $"), None);
+        assert_eq!(spans("costs $5 and $10"), [plain("costs $5 and $10")]);
+    }
+
+    #[test]
+    fn escaped_dollars_stay() {
+        assert_eq!(math_len("$x\\$"), None);
+        assert_eq!(spans("\\$x$"), [plain("$x$")]);
+    }
+
+    #[test]
+    fn math_is_rendered_inline() {
+        assert_eq!(spans("area $\\pi r^2$"), [plain("area πr²")]);
+        assert_eq!(spans("$$\\frac{1}{2}$$"), [plain("1/2")]);
+    }
+
+    #[test]
+    fn emphasis_toggles() {
+        assert_eq!(
+            spans("a **b** *c* ~~d~~"),
+            [
+                plain("a "),
+                ("b".to_string(), true, false, false),
+                plain(" "),
+                ("c".to_string(), false, true, false),
+                plain(" "),
+                ("d".to_string(), false, false, true),
+            ]
+        );
+        assert_eq!(
+            spans("__bold__ _it_"),
+            [
+                ("bold".to_string(), true, false, false),
+                plain(" "),
+                ("it".to_string(), false, true, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn emphasis_needs_a_partner_and_no_spaces() {
+        assert_eq!(spans("snake_case_name"), [plain("snake_case_name")]);
+        assert_eq!(spans("2 * 3 * 4"), [plain("2 * 3 * 4")]);
+        assert_eq!(spans("*unclosed"), [plain("*unclosed")]);
+        assert_eq!(spans("\\*not italic\\*"), [plain("*not italic*")]);
+    }
+
+    #[test]
+    fn list_items() {
+        assert_eq!(list_item("- item"), Some(("• ".to_string(), "item")));
+        assert_eq!(list_item("* item"), Some(("• ".to_string(), "item")));
+        assert_eq!(list_item("12. item"), Some(("12. ".to_string(), "item")));
+        assert_eq!(list_item("3) item"), Some(("3. ".to_string(), "item")));
+        assert_eq!(list_item("-item"), None);
+        assert_eq!(list_item("1.5 apples"), None);
+        assert_eq!(list_item("plain"), None);
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(heading("# Title"), Some((1, "Title")));
+        assert_eq!(heading("### Sub"), Some((3, "Sub")));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("####### seven"), None);
+        assert_eq!(heading("plain"), None);
+    }
+
+    #[test]
+    fn rules() {
+        assert!(is_rule("---"));
+        assert!(is_rule("* * *"));
+        assert!(is_rule("_____"));
+        assert!(!is_rule("--"));
+        assert!(!is_rule("-*-"));
+    }
+
+    #[test]
+    fn block_quotes_and_lists_are_prefixed() {
+        let text = |line: &Line| -> String { line.spans.iter().map(|s| s.text.as_str()).collect() };
+        let lines = render("> quoted\n- item\n  1. nested", 80);
+        let lines: Vec<String> = lines.iter().map(text).collect();
+        assert_eq!(lines, ["│ quoted", "• item", "  1. nested"]);
+    }
+}
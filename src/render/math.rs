@@ -0,0 +1,1075 @@
+//! LaTeX math as Unicode, `$...$` inline and `$$...$$` on lines of its own.
+//!
+//! Inline math is kept on one line, so fractions become `a/b`. Display math is laid out in two
+//! dimensions, with fractions stacked over a rule, limits above and below sums and integrals,
+//! and matrices and `\left(...\right)` in brackets as tall as what's inside.
+
+enum Node {
+    Symbol(String),
+    /// A binary operator or relation, spaced out unless it's unary.
+    Op(char),
+    /// Like `sin`, upright and followed by a space.
+    Function(String),
+    /// Like `∑` or `lim`, takes its scripts as limits above and below.
+    BigOp(String),
+    Row(Vec<Node>),
+    Frac(Box<Node>, Box<Node>),
+    Sqrt(Box<Node>),
+    Scripts {
+        base: Box<Node>,
+        sub: Option<Box<Node>>,
+        sup: Option<Box<Node>>,
+    },
+    Delimited {
+        open: Option<char>,
+        inner: Box<Node>,
+        close: Option<char>,
+    },
+    Matrix(Vec<Vec<Node>>),
+}
+
+fn greek(name: &str) -> Option<char> {
+    let c = match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        "Gamma" => 'Γ',
+        "Delta" => 'Δ',
+        "Theta" => 'Θ',
+        "Lambda" => 'Λ',
+        "Xi" => 'Ξ',
+        "Pi" => 'Π',
+        "Sigma" => 'Σ',
+        "Upsilon" => 'Υ',
+        "Phi" => 'Φ',
+        "Psi" => 'Ψ',
+        "Omega" => 'Ω',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn operator(name: &str) -> Option<char> {
+    let c = match name {
+        "leq" | "le" => '≤',
+        "geq" | "ge" => '≥',
+        "neq" | "ne" => '≠',
+        "approx" => '≈',
+        "equiv" => '≡',
+        "sim" => '∼',
+        "cong" => '≅',
+        "propto" => '∝',
+        "to" | "rightarrow" => '→',
+        "leftarrow" | "gets" => '←',
+        "leftrightarrow" => '↔',
+        "Rightarrow" | "implies" => '⇒',
+        "Leftarrow" => '⇐',
+        "Leftrightarrow" | "iff" => '⇔',
+        "mapsto" => '↦',
+        "in" => '∈',
+        "notin" => '∉',
+        "subset" => '⊂',
+        "subseteq" => '⊆',
+        "supset" => '⊃',
+        "supseteq" => '⊇',
+        "cup" => '∪',
+        "cap" => '∩',
+        "land" | "wedge" => '∧',
+        "lor" | "vee" => '∨',
+        "circ" => '∘',
+        "oplus" => '⊕',
+        "otimes" => '⊗',
+        "mid" => '∣',
+        "ll" => '≪',
+        "gg" => '≫',
+        "setminus" => '∖',
+        "cdot" => '⋅',
+        "times" => '×',
+        "div" => '÷',
+        "pm" => '±',
+        "mp" => '∓',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    let s = match name {
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "emptyset" | "varnothing" => "∅",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "prime" => "′",
+        "angle" => "∠",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "aleph" => "ℵ",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "|" => "‖",
+        "{" => "{",
+        "}" => "}",
+        "%" => "%",
+        "$" => "$",
+        "_" => "_",
+        "#" => "#",
+        "&" => "&",
+        "," | ":" | ";" | " " => " ",
+        "quad" => "  ",
+        "qquad" => "    ",
+        "!" => "",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn big_operator(name: &str) -> Option<&'static str> {
+    let s = match name {
+        "sum" => "∑",
+        "prod" => "∏",
+        "coprod" => "∐",
+        "int" => "∫",
+        "iint" => "∬",
+        "iiint" => "∭",
+        "oint" => "∮",
+        "bigcup" => "⋃",
+        "bigcap" => "⋂",
+        "lim" => "lim",
+        "liminf" => "lim inf",
+        "limsup" => "lim sup",
+        "max" => "max",
+        "min" => "min",
+        "sup" => "sup",
+        "inf" => "inf",
+        "det" => "det",
+        _ => return None,
+    };
+    Some(s)
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "log", "ln", "lg", "exp", "gcd", "deg", "dim", "ker", "Pr",
+];
+
+/// Combining marks for accents like `\hat{x}`.
+fn accent(name: &str) -> Option<char> {
+    let c = match name {
+        "hat" => '\u{302}',
+        "bar" | "overline" => '\u{305}',
+        "vec" => '\u{20D7}',
+        "dot" => '\u{307}',
+        "ddot" => '\u{308}',
+        "tilde" => '\u{303}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+fn blackboard(c: char) -> char {
+    match c {
+        'N' => 'ℕ',
+        'Z' => 'ℤ',
+        'Q' => 'ℚ',
+        'R' => 'ℝ',
+        'C' => 'ℂ',
+        'P' => 'ℙ',
+        c => c,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        self.pos += eaten as usize;
+        eaten
+    }
+
+    /// The chars from `pos` on.
+    fn rest(&self) -> &[char] {
+        self.chars.get(self.pos..).unwrap_or_default()
+    }
+
+    fn at_command(&self, name: &str) -> bool {
+        let rest = self.rest();
+        let len = name.chars().count();
+        // `\\` is a line break whatever comes after it, `\in` isn't the start of `\int`.
+        let ends = !name.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !rest.get(len + 1).is_some_and(char::is_ascii_alphabetic);
+        rest.first() == Some(&'\\')
+            && rest.iter().skip(1).take(len).copied().eq(name.chars())
+            && ends
+    }
+
+    /// The name after a backslash, letters or a single other char.
+    fn command(&mut self) -> String {
+        let letters: String = self
+            .rest()
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        if letters.is_empty() {
+            let c = self.peek().map(String::from).unwrap_or_default();
+            self.pos += c.chars().count();
+            return c;
+        }
+        self.pos += letters.len();
+        letters
+    }
+
+    /// What's between the braces, as it's written.
+    fn raw_group(&mut self) -> String {
+        self.skip_whitespace();
+        if !self.eat('{') {
+            return self
+                .peek()
+                .map(|c| {
+                    self.pos += 1;
+                    c.to_string()
+                })
+                .unwrap_or_default();
+        }
+        let mut depth = 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 1 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        text
+    }
+
+    /// Nodes up to the end of the group, a cell or a `\right`.
+    fn row(&mut self) -> Node {
+        let mut nodes: Vec<Node> = vec![];
+
+        loop {
+            self.skip_whitespace();
+            let Some(c) = self.peek() else { break };
+            if c == '}'
+                || c == '&'
+                || self.at_command("\\")
+                || self.at_command("right")
+                || self.at_command("end")
+            {
+                break;
+            }
+
+            if c == '^' || c == '_' {
+                self.pos += 1;
+                let script = Box::new(self.argument());
+                let base = nodes.pop().unwrap_or(Node::Symbol(String::new()));
+                let (base, mut sub, mut sup) = match base {
+                    Node::Scripts { base, sub, sup } => (base, sub, sup),
+                    base => (Box::new(base), None, None),
+                };
+                match c {
+                    '^' => sup = Some(script),
+                    _ => sub = Some(script),
+                }
+                nodes.push(Node::Scripts { base, sub, sup });
+                continue;
+            }
+
+            if let Some(node) = self.atom() {
+                nodes.push(node);
+            }
+        }
+
+        Node::Row(nodes)
+    }
+
+    /// What `^`, `_` or a command like `\frac` applies to, a group or a single atom.
+    fn argument(&mut self) -> Node {
+        self.skip_whitespace();
+        if self.eat('{') {
+            let row = self.row();
+            self.eat('}');
+            return row;
+        }
+        self.atom().unwrap_or(Node::Symbol(String::new()))
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        let c = self.peek()?;
+        self.pos += 1;
+        let node = match c {
+            '{' => {
+                let row = self.row();
+                self.eat('}');
+                row
+            }
+            '\\' => return self.command_node(),
+            '-' => Node::Op('−'),
+            '+' | '=' | '<' | '>' => Node::Op(c),
+            '\'' => Node::Symbol("′".to_string()),
+            c => Node::Symbol(c.to_string()),
+        };
+        Some(node)
+    }
+
+    fn delimiter(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        let c = self.peek()?;
+        self.pos += 1;
+        if c != '\\' {
+            return (c != '.').then_some(c);
+        }
+        match self.command().as_str() {
+            "{" | "lbrace" => Some('{'),
+            "}" | "rbrace" => Some('}'),
+            "|" | "Vert" => Some('‖'),
+            "vert" | "lvert" | "rvert" => Some('|'),
+            name => symbol(name).and_then(|s| s.chars().next()),
+        }
+    }
+
+    fn command_node(&mut self) -> Option<Node> {
+        let name = self.command();
+        let node = match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument();
+                let denominator = self.argument();
+                Node::Frac(Box::new(numerator), Box::new(denominator))
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                if self.eat('[') {
+                    // The index of an nth root isn't shown.
+                    while self.peek().is_some_and(|c| c != ']') {
+                        self.pos += 1;
+                    }
+                    self.eat(']');
+                }
+                Node::Sqrt(Box::new(self.argument()))
+            }
+            "left" => {
+                let open = self.delimiter();
+                let inner = self.row();
+                let close = match self.at_command("right") {
+                    true => {
+                        self.pos += "\\right".len();
+                        self.delimiter()
+                    }
+                    false => None,
+                };
+                Node::Delimited {
+                    open,
+                    inner: Box::new(inner),
+                    close,
+                }
+            }
+            "begin" => return Some(self.environment()),
+            "text" | "textrm" | "mathrm" | "mathit" | "mathbf" | "textbf" | "operatorname" => {
+                Node::Symbol(self.raw_group())
+            }
+            "mathbb" => Node::Symbol(self.raw_group().chars().map(blackboard).collect()),
+            name if FUNCTIONS.contains(&name) => Node::Function(name.to_string()),
+            name => {
+                if let Some(c) = greek(name) {
+                    Node::Symbol(c.to_string())
+                } else if let Some(c) = operator(name) {
+                    Node::Op(c)
+                } else if let Some(s) = symbol(name) {
+                    Node::Symbol(s.to_string())
+                } else if let Some(s) = big_operator(name) {
+                    Node::BigOp(s.to_string())
+                } else if let Some(mark) = accent(name) {
+                    let mut text = linear(&self.argument());
+                    text.push(mark);
+                    Node::Symbol(text)
+                } else {
+                    Node::Symbol(format!("\\{}", name))
+                }
+            }
+        };
+        Some(node)
+    }
+
+    /// A `\begin{...}` has been read, parses up to its `\end{...}`.
+    fn environment(&mut self) -> Node {
+        let name = self.raw_group();
+        let mut rows = vec![];
+        let mut cells = vec![];
+
+        loop {
+            cells.push(self.row());
+            if self.eat('&') {
+                continue;
+            }
+            rows.push(std::mem::take(&mut cells));
+            if self.at_command("\\") {
+                self.pos += 2;
+                continue;
+            }
+            if self.at_command("end") {
+                self.pos += "\\end".len();
+                self.raw_group();
+            }
+            break;
+        }
+
+        // A trailing `\\` leaves an empty last row.
+        if rows.len() > 1 && rows.last().is_some_and(|row| row.iter().all(is_empty)) {
+            rows.pop();
+        }
+
+        let (open, close) = match name.as_str() {
+            "pmatrix" => (Some('('), Some(')')),
+            "bmatrix" => (Some('['), Some(']')),
+            "Bmatrix" | "cases" => (Some('{'), None),
+            "vmatrix" => (Some('|'), Some('|')),
+            "Vmatrix" => (Some('‖'), Some('‖')),
+            _ => (None, None),
+        };
+        Node::Delimited {
+            open,
+            inner: Box::new(Node::Matrix(rows)),
+            close,
+        }
+    }
+}
+
+fn is_empty(node: &Node) -> bool {
+    matches!(node, Node::Row(nodes) if nodes.is_empty())
+}
+
+fn parse(text: &str) -> Node {
+    let mut parser = Parser::new(text);
+    let mut nodes = vec![];
+    // Stray closing braces and the like would stop a row early, so skip past them.
+    while parser.peek().is_some() {
+        nodes.push(parser.row());
+        if let Some(c) = parser.peek() {
+            parser.pos += 1;
+            nodes.push(Node::Symbol(c.to_string()));
+        }
+    }
+    Node::Row(nodes)
+}
+
+fn superscript(c: char) -> Option<char> {
+    let script = match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'f' => 'ᶠ',
+        'g' => 'ᵍ',
+        'h' => 'ʰ',
+        'i' => 'ⁱ',
+        'j' => 'ʲ',
+        'k' => 'ᵏ',
+        'l' => 'ˡ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        'r' => 'ʳ',
+        's' => 'ˢ',
+        't' => 'ᵗ',
+        'u' => 'ᵘ',
+        'v' => 'ᵛ',
+        'w' => 'ʷ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'z' => 'ᶻ',
+        'T' => 'ᵀ',
+        '′' => '′',
+        _ => return None,
+    };
+    Some(script)
+}
+
+fn subscript(c: char) -> Option<char> {
+    let script = match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// The script in Unicode super or subscript chars, if there's one for every char.
+fn to_script(node: &Node, script: fn(char) -> Option<char>) -> Option<String> {
+    linear(node)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(script)
+        .collect()
+}
+
+/// Wraps the text in parens unless it's a single symbol.
+fn parenthesize(text: String) -> String {
+    match text.chars().count() {
+        0 | 1 => text,
+        _ => format!("({})", text),
+    }
+}
+
+/// Functions and big operators get a space before their argument, unless it's in brackets.
+fn space_after(node: &Node, next: Option<&Node>) -> bool {
+    let spaced = match node {
+        Node::Function(_) | Node::BigOp(_) => true,
+        Node::Scripts { base, .. } => matches!(**base, Node::BigOp(_)),
+        _ => false,
+    };
+    spaced
+        && !matches!(
+            next,
+            None | Some(Node::Delimited { .. }) | Some(Node::Op(_))
+        )
+}
+
+/// The nodes of a row as text, operators spaced out unless they're unary.
+fn linear_row(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for (idx, node) in nodes.iter().enumerate() {
+        let unary = idx == 0 || matches!(nodes[idx - 1], Node::Op(_));
+        match node {
+            Node::Op(c) if !unary => text.push_str(&format!(" {} ", c)),
+            node => {
+                text.push_str(&linear(node));
+                if space_after(node, nodes.get(idx + 1)) {
+                    text.push(' ');
+                }
+            }
+        }
+    }
+    text
+}
+
+/// The math on one line.
+fn linear(node: &Node) -> String {
+    match node {
+        Node::Symbol(text) | Node::Function(text) | Node::BigOp(text) => text.clone(),
+        Node::Op(c) => c.to_string(),
+        Node::Row(nodes) => linear_row(nodes),
+        Node::Frac(numerator, denominator) => format!(
+            "{}/{}",
+            parenthesize(linear(numerator)),
+            parenthesize(linear(denominator))
+        ),
+        Node::Sqrt(inner) => format!("√{}", parenthesize(linear(inner))),
+        Node::Scripts { base, sub, sup } => {
+            let mut text = linear(base);
+            for (script, unicode, ascii) in [
+                (sub, subscript as fn(char) -> Option<char>, '_'),
+                (sup, superscript, '^'),
+            ] {
+                let Some(script) = script else { continue };
+                match to_script(script, unicode) {
+                    Some(chars) => text.push_str(&chars),
+                    None => {
+                        text.push(ascii);
+                        text.push_str(&parenthesize(linear(script)));
+                    }
+                }
+            }
+            text
+        }
+        Node::Delimited { open, inner, close } => {
+            let mut text = String::from_iter(*open);
+            text.push_str(&linear(inner));
+            text.extend(*close);
+            text
+        }
+        Node::Matrix(rows) => rows
+            .iter()
+            .map(|row| row.iter().map(linear).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+/// Inline math, on one line.
+pub fn inline(text: &str) -> String {
+    linear(&parse(text))
+}
+
+/// A rectangle of text, with the row that lines up with the text around it.
+#[derive(Clone)]
+struct Block {
+    rows: Vec<Vec<char>>,
+    baseline: usize,
+}
+
+impl Block {
+    fn text(text: &str) -> Self {
+        Self {
+            rows: vec![text.chars().collect()],
+            baseline: 0,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or_default()
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Pads the rows with spaces to `width`, the block in the middle.
+    fn centered(&self, width: usize) -> Vec<Vec<char>> {
+        let left = (width.saturating_sub(self.width())) / 2;
+        self.rows
+            .iter()
+            .map(|row| padded(row, left, width))
+            .collect()
+    }
+}
+
+/// The row with `left` spaces in front of it and spaces after it up to `width`.
+fn padded(row: &[char], left: usize, width: usize) -> Vec<char> {
+    let mut padded = vec![' '; left];
+    padded.extend(row);
+    padded.resize(width, ' ');
+    padded
+}
+
+/// The blocks side by side, their baselines lined up.
+fn beside(blocks: Vec<Block>) -> Block {
+    let above = blocks
+        .iter()
+        .map(|block| block.baseline)
+        .max()
+        .unwrap_or_default();
+    let below = blocks
+        .iter()
+        .map(|block| block.height() - block.baseline - 1)
+        .max()
+        .unwrap_or_default();
+    let mut rows = vec![vec![]; above + below + 1];
+
+    for block in blocks {
+        let width = block.width();
+        let top = above - block.baseline;
+        for (idx, row) in rows.iter_mut().enumerate() {
+            let mut part = match idx.checked_sub(top).and_then(|idx| block.rows.get(idx)) {
+                Some(part) => part.clone(),
+                None => vec![],
+            };
+            part.resize(width, ' ');
+            row.extend(part);
+        }
+    }
+
+    Block {
+        rows,
+        baseline: above,
+    }
+}
+
+/// The blocks on top of each other, each centered.
+fn stacked(blocks: Vec<Block>, baseline: usize) -> Block {
+    let width = blocks.iter().map(Block::width).max().unwrap_or_default();
+    Block {
+        rows: blocks
+            .iter()
+            .flat_map(|block| block.centered(width))
+            .collect(),
+        baseline,
+    }
+}
+
+/// A bracket as tall as `height`.
+fn delimiter(c: char, height: usize, baseline: usize) -> Block {
+    let (top, middle, bottom, center) = match c {
+        '(' => ('⎛', '⎜', '⎝', '⎜'),
+        ')' => ('⎞', '⎟', '⎠', '⎟'),
+        '[' => ('⎡', '⎢', '⎣', '⎢'),
+        ']' => ('⎤', '⎥', '⎦', '⎥'),
+        '{' => ('⎧', '⎪', '⎩', '⎨'),
+        '}' => ('⎫', '⎪', '⎭', '⎬'),
+        '⌈' => ('⎡', '⎢', '⎢', '⎢'),
+        '⌉' => ('⎤', '⎥', '⎥', '⎥'),
+        '⌊' => ('⎢', '⎢', '⎣', '⎢'),
+        '⌋' => ('⎥', '⎥', '⎦', '⎥'),
+        '|' => ('│', '│', '│', '│'),
+        c => (' ', ' ', ' ', c),
+    };
+
+    let rows = (0..height)
+        .map(|idx| {
+            let c = if idx == 0 {
+                top
+            } else if idx == height - 1 {
+                bottom
+            } else if idx == height / 2 {
+                center
+            } else {
+                middle
+            };
+            vec![c]
+        })
+        .collect();
+    Block { rows, baseline }
+}
+
+fn layout_row(nodes: &[Node]) -> Block {
+    let mut blocks = vec![];
+    for (idx, node) in nodes.iter().enumerate() {
+        let unary = idx == 0 || matches!(nodes[idx - 1], Node::Op(_));
+        match node {
+            Node::Op(c) if !unary => blocks.push(Block::text(&format!(" {} ", c))),
+            node => {
+                blocks.push(layout(node));
+                if space_after(node, nodes.get(idx + 1)) {
+                    blocks.push(Block::text(" "));
+                }
+            }
+        }
+    }
+    if blocks.is_empty() {
+        return Block::text("");
+    }
+    beside(blocks)
+}
+
+fn layout(node: &Node) -> Block {
+    match node {
+        Node::Symbol(_) | Node::Op(_) | Node::Function(_) | Node::BigOp(_) => {
+            Block::text(&linear(node))
+        }
+        Node::Row(nodes) => layout_row(nodes),
+        Node::Frac(numerator, denominator) => {
+            let numerator = layout(numerator);
+            let denominator = layout(denominator);
+            let width = numerator.width().max(denominator.width());
+            let baseline = numerator.height();
+            let rule = Block::text(&"─".repeat(width));
+            stacked(vec![numerator, rule, denominator], baseline)
+        }
+        Node::Sqrt(inner) => {
+            let inner = layout(inner);
+            let width = inner.width();
+            let height = inner.height();
+            let mut rows = vec![padded(&vec!['_'; width], 1, width + 1)];
+            for (idx, row) in inner.rows.iter().enumerate() {
+                let mut row = padded(row, 1, width + 1);
+                row[0] = if idx == height - 1 { '√' } else { '│' };
+                rows.push(row);
+            }
+            Block {
+                rows,
+                baseline: inner.baseline + 1,
+            }
+        }
+        Node::Scripts { base, sub, sup } => {
+            let base_block = layout(base);
+            let sub_text = sub.as_ref().map(|sub| to_script(sub, subscript));
+            let sup_text = sup.as_ref().map(|sup| to_script(sup, superscript));
+            let fits_on_line = !matches!(**base, Node::BigOp(_))
+                && base_block.height() == 1
+                && !matches!(sub_text, Some(None))
+                && !matches!(sup_text, Some(None));
+            if fits_on_line {
+                return Block::text(&linear(node));
+            }
+
+            let sup = sup.as_ref().map(|sup| layout(sup));
+            let sub = sub.as_ref().map(|sub| layout(sub));
+            let sup_height = sup.as_ref().map(Block::height).unwrap_or_default();
+
+            if matches!(**base, Node::BigOp(_)) {
+                let baseline = sup_height + base_block.baseline;
+                let blocks = sup.into_iter().chain([base_block]).chain(sub).collect();
+                return stacked(blocks, baseline);
+            }
+
+            // The scripts go right of the base, above and below it.
+            let base_width = base_block.width();
+            let script_width = sup
+                .iter()
+                .chain(&sub)
+                .map(Block::width)
+                .max()
+                .unwrap_or_default();
+            let width = base_width + script_width;
+            let scripts = |block: &Option<Block>| -> Vec<Vec<char>> {
+                block
+                    .iter()
+                    .flat_map(|block| &block.rows)
+                    .map(|row| padded(row, base_width, width))
+                    .collect()
+            };
+
+            let mut rows = scripts(&sup);
+            rows.extend(base_block.rows.iter().map(|row| padded(row, 0, width)));
+            rows.extend(scripts(&sub));
+            Block {
+                rows,
+                baseline: sup_height + base_block.baseline,
+            }
+        }
+        Node::Delimited { open, inner, close } => {
+            let inner = layout(inner);
+            let (height, baseline) = (inner.height(), inner.baseline);
+            if height == 1 {
+                return Block::text(&linear(node));
+            }
+            let mut blocks = vec![];
+            blocks.extend(open.map(|c| delimiter(c, height, baseline)));
+            blocks.push(inner);
+            blocks.extend(close.map(|c| delimiter(c, height, baseline)));
+            beside(blocks)
+        }
+        Node::Matrix(rows) => {
+            let cells: Vec<Vec<Block>> = rows
+                .iter()
+                .map(|row| row.iter().map(layout).collect())
+                .collect();
+            let columns = cells.iter().map(Vec::len).max().unwrap_or_default();
+            let widths: Vec<usize> = (0..columns)
+                .map(|column| {
+                    cells
+                        .iter()
+                        .filter_map(|row| row.get(column))
+                        .map(Block::width)
+                        .max()
+                        .unwrap_or_default()
+                })
+                .collect();
+
+            let rows: Vec<Block> = cells
+                .iter()
+                .map(|row| {
+                    let mut blocks = vec![Block::text(" ")];
+                    for (column, width) in widths.iter().enumerate() {
+                        if column > 0 {
+                            blocks.push(Block::text("  "));
+                        }
+                        let cell = row.get(column).cloned().unwrap_or_else(|| Block::text(""));
+                        blocks.push(Block {
+                            rows: cell.centered(*width),
+                            baseline: cell.baseline,
+                        });
+                    }
+                    blocks.push(Block::text(" "));
+                    beside(blocks)
+                })
+                .collect();
+
+            let height: usize = rows.iter().map(Block::height).sum();
+            stacked(rows, height.saturating_sub(1) / 2)
+        }
+    }
+}
+
+/// Display math, laid out on as many lines as it needs.
+pub fn display(text: &str) -> Vec<String> {
+    layout(&parse(text))
+        .rows
+        .into_iter()
+        .map(|row| row.into_iter().collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_fractions() {
+        assert_eq!(inline(r"\frac{1}{2}"), "1/2");
+        assert_eq!(inline(r"\frac{a+b}{c}"), "(a + b)/c");
+        assert_eq!(inline(r"\frac12"), "1/2");
+    }
+
+    #[test]
+    fn inline_scripts() {
+        assert_eq!(inline("x^2"), "x²");
+        assert_eq!(inline("a_{ij}"), "aᵢⱼ");
+        assert_eq!(inline("x_1^2"), "x₁²");
+        // No superscript q, so it falls back to a caret.
+        assert_eq!(inline("x^{q}"), "x^q");
+        assert_eq!(inline("e^{i\\pi}"), "e^(iπ)");
+    }
+
+    #[test]
+    fn symbols_and_operators() {
+        assert_eq!(inline(r"\alpha \leq \beta"), "α ≤ β");
+        assert_eq!(inline("-x"), "−x");
+        assert_eq!(inline(r"\mathbb{R}"), "ℝ");
+        assert_eq!(inline(r"\sin x"), "sin x");
+        assert_eq!(inline(r"\unknown"), "\\unknown");
+    }
+
+    #[test]
+    fn display_fractions_are_stacked() {
+        assert_eq!(display(r"\frac{1}{2}"), ["1", "─", "2"]);
+        assert_eq!(display(r"x = \frac{a}{bc}"), ["    a", "x = ──", "    bc"]);
+    }
+
+    #[test]
+    fn display_limits_go_above_and_below() {
+        assert_eq!(display(r"\sum_{i=0}^{n} i"), ["  n", "  ∑   i", "i = 0"]);
+    }
+
+    #[test]
+    fn left_right_brackets_grow() {
+        assert_eq!(display(r"\left( x \right)"), ["(x)"]);
+        assert_eq!(
+            display(r"\left( \frac{a}{b} \right)"),
+            ["⎛a⎞", "⎜─⎟", "⎝b⎠"]
+        );
+        assert_eq!(inline(r"\left. x \right|"), "x|");
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!(
+            display(r"\begin{pmatrix} 1 & 2 \\ 3 & 4 \end{pmatrix}"),
+            ["⎛ 1  2 ⎞", "⎝ 3  4 ⎠"]
+        );
+        assert_eq!(
+            inline(r"\begin{bmatrix} a & b \\ c & d \\ \end{bmatrix}"),
+            "[a b; c d]"
+        );
+    }
+
+    #[test]
+    fn malformed_input_doesnt_panic() {
+        let inputs = [
+            "{",
+            "{{x",
+            "}",
+            "x}}",
+            "\\",
+            "x\\",
+            "\\\\",
+            "^",
+            "_{",
+            "x^",
+            r"\frac",
+            r"\frac{a",
+            r"\frac{a}{",
+            r"\sqrt[3",
+            r"\left(",
+            r"\left",
+            r"\right)",
+            r"\begin{pmatrix",
+            r"\begin{pmatrix} a &",
+            r"\begin{pmatrix} a \\",
+            r"\end{pmatrix}",
+            "&&",
+            r"\text{unclosed",
+            r"\hat",
+        ];
+        for input in inputs {
+            inline(input);
+            display(input);
+        }
+    }
+}
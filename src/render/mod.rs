@@ -0,0 +1,302 @@
+//! Turns the text of a card face into styled lines that fit the terminal.
+
+use std::io::{Stdout, Write};
+
+use crossterm::cursor::MoveTo;
+use crossterm::execute;
+use crossterm::style::{ContentStyle, PrintStyledContent};
+use crossterm::terminal;
+
+pub mod code;
+pub mod layout;
+pub mod markdown;
+pub mod math;
+
+/// A run of text with the same style.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub style: ContentStyle,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, style: ContentStyle) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self::new(text, ContentStyle::new())
+    }
+}
+
+/// One row on screen.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Line {
+    pub spans: Vec<Span>,
+    /// A line of a code block. It keeps its indentation and scrolls sideways instead of wrapping.
+    pub code: bool,
+}
+
+impl Line {
+    pub fn new(spans: Vec<Span>) -> Self {
+        Self { spans, code: false }
+    }
+
+    /// In chars.
+    pub fn len(&self) -> usize {
+        self.spans
+            .iter()
+            .map(|span| span.text.chars().count())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.iter().all(|span| span.text.is_empty())
+    }
+
+    /// Adds a char, merging it into the last span if the style is the same.
+    pub fn push(&mut self, c: char, style: ContentStyle) {
+        match self.spans.last_mut() {
+            Some(span) if span.style == style => span.text.push(c),
+            _ => self.spans.push(Span::new(c, style)),
+        }
+    }
+
+    fn chars(&self) -> impl Iterator<Item = (char, ContentStyle)> + '_ {
+        self.spans
+            .iter()
+            .flat_map(|span| span.text.chars().map(|c| (c, span.style)))
+    }
+
+    /// `len` chars from `from` on.
+    fn slice(&self, from: usize, len: usize) -> Line {
+        let mut line = Line::default();
+        for (c, style) in self.chars().skip(from).take(len) {
+            line.push(c, style);
+        }
+        line
+    }
+}
+
+/// Word wraps `spans` to `width` chars. The first row starts with `first` and the rest with
+/// `rest`, like a list bullet and the spaces that line up under it.
+pub fn wrap(spans: Vec<Span>, width: usize, first: Span, rest: Span) -> Vec<Line> {
+    let first_width = width.saturating_sub(first.text.chars().count()).max(1);
+    let rest_width = width.saturating_sub(rest.text.chars().count()).max(1);
+    let width = |lines: &Vec<Line>| match lines.is_empty() {
+        true => first_width,
+        false => rest_width,
+    };
+    let text = Line::new(spans);
+
+    let mut lines = vec![];
+    let mut line = Line::default();
+    let mut word = Line::default();
+    // The whitespace between the end of the line and the word, dropped if the word wraps.
+    let mut space = Line::default();
+
+    let flush = |line: &mut Line, word: &mut Line, space: &mut Line, lines: &mut Vec<Line>| {
+        if word.is_empty() {
+            return;
+        }
+        if !line.is_empty() && line.len() + space.len() + word.len() > width(lines) {
+            lines.push(std::mem::take(line));
+            space.spans.clear();
+        }
+        for (c, style) in space.chars().chain(word.chars()) {
+            if line.len() == width(lines) {
+                lines.push(std::mem::take(line));
+            }
+            line.push(c, style);
+        }
+        space.spans.clear();
+        word.spans.clear();
+    };
+
+    for (c, style) in text.chars() {
+        if c.is_whitespace() {
+            flush(&mut line, &mut word, &mut space, &mut lines);
+            space.push(c, style);
+        } else {
+            word.push(c, style);
+        }
+    }
+    flush(&mut line, &mut word, &mut space, &mut lines);
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    for (idx, line) in lines.iter_mut().enumerate() {
+        let prefix = if idx == 0 { &first } else { &rest };
+        if !prefix.text.is_empty() {
+            line.spans.insert(0, prefix.clone());
+        }
+    }
+    lines
+}
+
+pub fn terminal_width() -> usize {
+    terminal::size()
+        .map(|(width, _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// The lines to draw for the text of a card face.
+pub fn render(text: &str, width: usize) -> Vec<Line> {
+    markdown::render(text, width)
+}
+
+/// Lays `style` under the lines that aren't code, the spans keep their own colors on top of it.
+pub fn tint(lines: &mut [Line], style: ContentStyle) {
+    let lines = lines.iter_mut().filter(|line| !line.code);
+    for span in lines.flat_map(|line| &mut line.spans) {
+        let own = &mut span.style;
+        own.foreground_color = own.foreground_color.or(style.foreground_color);
+        own.background_color = own.background_color.or(style.background_color);
+        own.attributes.extend(style.attributes);
+    }
+}
+
+/// How far the lines can scroll sideways before the longest line of code is out of sight.
+fn max_scroll(lines: &[Line], width: usize) -> usize {
+    lines
+        .iter()
+        .filter(|line| line.code)
+        .map(|line| line.len().saturating_sub(width))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Prints a line where the cursor is, a line of code scrolled `scroll` chars to the right.
+fn print_line(stdout: &mut Stdout, line: &Line, scroll: usize, width: usize) {
+    let line = match line.code {
+        true => line.slice(scroll, width),
+        false => line.clone(),
+    };
+    for span in &line.spans {
+        execute!(
+            stdout,
+            PrintStyledContent(span.style.apply(span.text.as_str()))
+        )
+        .unwrap();
+    }
+}
+
+/// Prints the lines from the start of the cursor's row down, with the lines of code scrolled
+/// `scroll` chars to the right.
+pub fn print_lines(stdout: &mut Stdout, lines: &[Line], scroll: usize) {
+    let width = terminal_width();
+    let scroll = scroll.min(max_scroll(lines, width));
+
+    for line in lines {
+        let (_, y) = crossterm::cursor::position().unwrap();
+        execute!(stdout, MoveTo(0, y)).unwrap();
+        print_line(stdout, line, scroll, width);
+        println!();
+    }
+    let (_, y) = crossterm::cursor::position().unwrap();
+    execute!(stdout, MoveTo(0, y)).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Prints the lines on the rows from `top` down, without moving past the last one, so the
+/// bottom row of the screen can be used without scrolling it.
+pub fn print_rows(stdout: &mut Stdout, top: u16, lines: &[Line], scroll: usize) {
+    let width = terminal_width();
+    let scroll = scroll.min(max_scroll(lines, width));
+
+    for (row, line) in lines.iter().enumerate() {
+        execute!(stdout, MoveTo(0, top + row as u16)).unwrap();
+        print_line(stdout, line, scroll, width);
+    }
+}
+
+/// Word wraps plain text to the terminal, the first row starting `column` chars in.
+pub fn wrap_text(text: &str, column: usize) -> Vec<String> {
+    let width = terminal_width();
+    text.split('\n')
+        .enumerate()
+        .flat_map(|(idx, line)| {
+            let indent = if idx == 0 { column } else { 0 };
+            let first = Span::plain(" ".repeat(indent));
+            wrap(vec![Span::plain(line)], width, first, Span::plain(""))
+        })
+        .map(|line| {
+            let text: String = line.spans.iter().map(|span| span.text.as_str()).collect();
+            text
+        })
+        .enumerate()
+        .map(|(idx, text)| match idx {
+            0 => text.chars().skip(column).collect(),
+            _ => text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn wrapped(text: &str, width: usize, first: &str, rest: &str) -> Vec<String> {
+        let spans = vec![Span::plain(text)];
+        wrap(spans, width, Span::plain(first), Span::plain(rest))
+            .iter()
+            .map(self::text)
+            .collect()
+    }
+
+    #[test]
+    fn wraps_at_word_boundaries() {
+        assert_eq!(
+            wrapped("the quick brown fox jumps", 10, "", ""),
+            ["the quick", "brown fox", "jumps"]
+        );
+    }
+
+    #[test]
+    fn breaks_words_longer_than_the_width() {
+        assert_eq!(wrapped("abcdefghij", 4, "", ""), ["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn prefixes_take_up_width() {
+        assert_eq!(
+            wrapped("one two three", 9, "- ", "  "),
+            ["- one two", "  three"]
+        );
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        assert_eq!(wrapped("ææææ øøøø", 4, "", ""), ["ææææ", "øøøø"]);
+    }
+
+    #[test]
+    fn keeps_an_empty_line() {
+        assert_eq!(wrapped("", 10, "> ", "> "), ["> "]);
+    }
+
+    #[test]
+    fn keeps_the_styles() {
+        let mut style = ContentStyle::new();
+        style.attributes.set(crossterm::style::Attribute::Bold);
+        let bold = Span::new("bold", style);
+        let spans = vec![Span::plain("not "), bold.clone()];
+        let lines = wrap(spans, 4, Span::plain(""), Span::plain(""));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].spans, [bold]);
+    }
+
+    #[test]
+    fn indents_by_chars() {
+        let lines = render("\u{3000}\u{3000}indented", 80);
+        assert_eq!(text(&lines[0]), "  indented");
+    }
+}
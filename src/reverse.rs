@@ -0,0 +1,103 @@
+//! Reverse cards, made by swapping the front and back of a card and kept in sync with it.
+//!
+//! Which cards are each other's reverse is kept in `reverse_links.toml` in the share folder.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use speki_backend::card::{Card, CardCache, SavedCard};
+use speki_backend::paths::get_share_path;
+use speki_backend::Id;
+
+use crate::cloze;
+use crate::undo::Change;
+
+fn path() -> PathBuf {
+    get_share_path().join("reverse_links.toml")
+}
+
+/// Card id to the id of its reverse, both ways round.
+fn load_links() -> BTreeMap<String, String> {
+    std::fs::read_to_string(path())
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_links(links: &BTreeMap<String, String>) {
+    std::fs::write(path(), toml::to_string(links).unwrap()).unwrap();
+}
+
+pub fn link(a: &Id, b: &Id) {
+    let mut links = load_links();
+    links.insert(a.to_string(), b.to_string());
+    links.insert(b.to_string(), a.to_string());
+    save_links(&links);
+}
+
+/// The card's reverse, if it has one that still exists.
+pub fn sibling(card: &Id, cache: &CardCache) -> Option<Id> {
+    let sibling: Id = load_links().get(&card.to_string())?.parse().ok()?;
+    cache.exists(&sibling).then_some(sibling)
+}
+
+/// Cards without a back and cloze cards can't have a reverse.
+pub fn can_reverse(back: &str) -> bool {
+    !back.trim().is_empty() && cloze::index(back).is_none()
+}
+
+/// Makes a card with the front and back swapped. Cards that [can't have a reverse](can_reverse)
+/// or already have one get `None`.
+pub fn create(card: &SavedCard, cache: &mut CardCache) -> Option<SavedCard> {
+    if !can_reverse(card.back_text()) || sibling(card.id(), cache).is_some() {
+        return None;
+    }
+
+    let mut reverse = Card::new_simple(card.back_text().to_owned(), card.front_text().to_owned());
+    reverse.meta.finished = card.is_finished();
+    let reverse = reverse.save_new_card(card.category(), cache);
+
+    let mut change = Change::new("add reverse card");
+    change.created(reverse.as_path());
+    change.record();
+
+    link(card.id(), reverse.id());
+    Some(reverse)
+}
+
+/// Gives the card's reverse the edited card's text, swapped.
+pub fn sync(edited: &SavedCard, cache: &mut CardCache) {
+    let Some(sibling) = sibling(edited.id(), cache) else {
+        return;
+    };
+
+    let mut reverse = cache.get_owned(&sibling);
+    if reverse.front_text() != edited.back_text() {
+        reverse.set_front_text(edited.back_text());
+    }
+    if reverse.back_text() != edited.front_text() {
+        reverse.set_back_text(edited.front_text());
+    }
+    cache.refresh();
+}
+
+/// Drops cards whose reverse was reviewed already or comes earlier in the queue.
+pub fn keep_apart(cards: Vec<Id>, reviewed: &BTreeSet<Id>, cache: &CardCache) -> Vec<Id> {
+    let links = load_links();
+    let mut seen = reviewed.clone();
+    let mut kept = vec![];
+
+    for card in cards {
+        let sibling = links
+            .get(&card.to_string())
+            .and_then(|sibling| sibling.parse::<Id>().ok())
+            .filter(|sibling| cache.exists(sibling));
+        if sibling.is_some_and(|sibling| seen.contains(&sibling)) {
+            continue;
+        }
+        seen.insert(card);
+        kept.push(card);
+    }
+
+    kept
+}
@@ -0,0 +1,161 @@
+//! What happened during a review session, shown when it ends and kept in `sessions.log`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+use speki_backend::card::CardCache;
+use speki_backend::common::{current_time, duration_to_days};
+use speki_backend::paths::get_share_path;
+use speki_backend::Id;
+
+pub struct Session {
+    started: Duration,
+    /// Every review in order, with the key it was graded with and the time it took.
+    reviews: Vec<(Id, char, Duration)>,
+    /// Strength in days and whether the card was resolved, from before its first review.
+    before: HashMap<Id, (f32, bool)>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            started: current_time(),
+            reviews: vec![],
+            before: HashMap::new(),
+        }
+    }
+}
+
+fn strength_and_resolved(card: &Id, cache: &mut CardCache) -> (f32, bool) {
+    let card = cache.get_ref(card);
+    let strength = card
+        .strength()
+        .map(|strength| duration_to_days(&strength))
+        .unwrap_or_default();
+    (strength, card.is_resolved(cache))
+}
+
+impl Session {
+    /// Call before the review is saved so the card's state from before it can be kept.
+    pub fn review(&mut self, card: &Id, grade: char, duration: Duration, cache: &mut CardCache) {
+        if !self.before.contains_key(card) {
+            let before = strength_and_resolved(card, cache);
+            self.before.insert(*card, before);
+        }
+        self.reviews.push((*card, grade, duration));
+    }
+
+    /// Forgets the last review of the card, after it was undone.
+    pub fn undo_review(&mut self, card: &Id) {
+        if let Some(index) = self.reviews.iter().rposition(|(id, _, _)| id == card) {
+            self.reviews.remove(index);
+        }
+        if !self.reviews.iter().any(|(id, _, _)| id == card) {
+            self.before.remove(card);
+        }
+    }
+
+    /// The cards reviewed so far.
+    pub fn reviewed(&self) -> BTreeSet<Id> {
+        self.before.keys().copied().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reviews.is_empty()
+    }
+
+    pub fn summary(&self, cache: &mut CardCache) -> Summary {
+        let mut grades = BTreeMap::new();
+        for (_, grade, _) in &self.reviews {
+            *grades.entry(*grade).or_default() += 1;
+        }
+
+        let total_time: Duration = self.reviews.iter().map(|(_, _, time)| *time).sum();
+        let average_time = total_time
+            .checked_div(self.reviews.len() as u32)
+            .unwrap_or_default();
+
+        let mut newly_resolved = 0;
+        let mut strength_change = 0.;
+        for (card, (strength, resolved)) in &self.before {
+            if !cache.exists(card) {
+                continue;
+            }
+            let (strength_now, resolved_now) = strength_and_resolved(card, cache);
+            newly_resolved += (resolved_now && !resolved) as usize;
+            strength_change += strength_now - strength;
+        }
+
+        Summary {
+            started: self.started,
+            length: current_time().saturating_sub(self.started),
+            reviewed: self.reviews.len(),
+            grades,
+            average_time,
+            newly_resolved,
+            strength_change,
+        }
+    }
+}
+
+pub struct Summary {
+    started: Duration,
+    length: Duration,
+    reviewed: usize,
+    grades: BTreeMap<char, usize>,
+    average_time: Duration,
+    newly_resolved: usize,
+    /// In days, summed over the reviewed cards.
+    strength_change: f32,
+}
+
+impl Summary {
+    fn grades(&self) -> String {
+        self.grades
+            .iter()
+            .map(|(grade, qty)| format!("{grade}: {qty}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Adds a line with the summary to `sessions.log` in the share folder.
+    pub fn append_to_log(&self) {
+        let path = get_share_path().join("sessions.log");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        writeln!(
+            file,
+            "{}\t{}s\treviewed: {}\tgrades: {}\taverage: {:.1}s\tnewly resolved: {}\tstrength: {:+.2}",
+            self.started.as_secs(),
+            self.length.as_secs(),
+            self.reviewed,
+            self.grades(),
+            self.average_time.as_secs_f32(),
+            self.newly_resolved,
+            self.strength_change,
+        )
+        .unwrap();
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Session over!\n\nreviewed: {}\ngrades: {}\naverage time: {:.1}s\nnewly resolved: {}\nstrength change: {:+.2} days\ntime spent: {}m {}s",
+            self.reviewed,
+            self.grades(),
+            self.average_time.as_secs_f32(),
+            self.newly_resolved,
+            self.strength_change,
+            self.length.as_secs() / 60,
+            self.length.as_secs() % 60,
+        )
+    }
+}
@@ -0,0 +1,149 @@
+//! The numbers behind the stats screens, kept apart from the drawing so the cli can print them too.
+
+use std::fmt;
+
+use serde::Serialize;
+use speki_backend::card::{CardCache, SavedCard};
+use speki_backend::common::duration_to_days;
+
+use crate::cloze;
+
+#[derive(Serialize, Default)]
+pub struct Stats {
+    pub suspended: i32,
+    pub finished: i32,
+    pub pending: i32,
+    pub reviews: usize,
+    /// Sum of the strength of every card, in days.
+    pub strength: i32,
+    pub resolved: i32,
+    /// Roughly how many reviews a day it takes to keep the confidently resolved cards.
+    pub daily_cards: f32,
+    pub total_cards: usize,
+}
+
+impl Stats {
+    pub fn collect(cache: &mut CardCache) -> Self {
+        let cards = SavedCard::load_all_cards();
+        let mut stats = Self {
+            total_cards: cards.len(),
+            ..Default::default()
+        };
+
+        for card in cards {
+            stats.pending += card.stability().is_none() as i32;
+            stats.reviews += card.reviews().len();
+            stats.finished += card.is_finished() as i32;
+            stats.resolved += card.is_resolved(cache) as i32;
+            stats.strength +=
+                (card.strength().unwrap_or_default().as_secs_f32() / 86400.).round() as i32;
+            stats.suspended += card.is_suspended() as i32;
+
+            if let Some(stability) = card.stability() {
+                if card.is_confidently_resolved(cache) {
+                    let mut days = 1. / duration_to_days(&stability);
+                    // if stability is like 0.1 it still feels dumb to say that it's on average 10 cards a day for one card lol
+                    if days > 1.0 {
+                        days = 1.0;
+                    }
+                    stats.daily_cards += days;
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "suspended: {}\nfinished: {}\npending: {}\nreviews: {}\nstrength: {}\nresolved: {}\ndaily cards: {}\ntotal cards: {}",
+            self.suspended,
+            self.finished,
+            self.pending,
+            self.reviews,
+            self.strength,
+            self.resolved,
+            self.daily_cards,
+            self.total_cards
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct CardStats {
+    pub id: String,
+    pub front: String,
+    pub category: String,
+    pub gain: f32,
+    /// In days.
+    pub stability: f32,
+    /// In percent.
+    pub recall: f32,
+    /// More time has passed since the last review than the stability.
+    pub overdue: bool,
+}
+
+impl CardStats {
+    /// `None` for cards that haven't been reviewed yet.
+    pub fn new(card: &SavedCard) -> Option<Self> {
+        let (Some(gain), Some(stability), Some(recall), Some(since_review)) = (
+            card.expected_gain(),
+            card.stability(),
+            card.recall_rate(),
+            card.time_since_last_review(),
+        ) else {
+            return None;
+        };
+        let stability = (stability.as_secs_f32() / 864.).round() / 100.;
+
+        Some(Self {
+            id: card.id().to_string(),
+            front: cloze::question(card.front_text(), card.back_text()),
+            category: card.category().print_full(),
+            gain: (gain * 100.).round() / 100.,
+            stability,
+            recall: (recall * 100.).round(),
+            overdue: stability < since_review.as_secs_f32() / 86400.,
+        })
+    }
+}
+
+impl fmt::Display for CardStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gain: {}, stability: {}days, recall: {}%, overdue: {}, card: {}",
+            self.gain, self.stability, self.recall, self.overdue, self.front
+        )
+    }
+}
+
+/// Every reviewed card, lowest expected gain first.
+pub fn expected_gains() -> Vec<CardStats> {
+    let mut cards: Vec<CardStats> = SavedCard::load_all_cards()
+        .iter()
+        .filter_map(CardStats::new)
+        .collect();
+
+    cards.sort_by(|a, b| a.gain.total_cmp(&b.gain));
+    cards
+}
+
+/// What `speki stats --json` prints.
+#[derive(Serialize)]
+pub struct StatsReport {
+    pub totals: Stats,
+    pub cards: Vec<CardStats>,
+}
+
+impl StatsReport {
+    pub fn collect(cache: &mut CardCache) -> Self {
+        Self {
+            totals: Stats::collect(cache),
+            cards: expected_gains(),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! How the ui is colored, picked with `theme` in the same `config.toml` as the backend's settings.
+//!
+//! When `NO_COLOR` is set or the terminal can't show colors, the high contrast theme is used
+//! without its colors, since it tells things apart with attributes like bold and reverse.
+
+use std::sync::OnceLock;
+
+use crossterm::style::{available_color_count, Attribute, Color, ContentStyle};
+use serde::Deserialize;
+
+use crate::config::config;
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+pub struct Theme {
+    /// The selected item of lists and menus.
+    pub selection: ContentStyle,
+    /// The chars the fuzzy filter matched.
+    pub highlight: ContentStyle,
+    pub status_bar: ContentStyle,
+    pub front: ContentStyle,
+    pub back: ContentStyle,
+    /// The line between the front and the back, and the signs marking them in the editor.
+    pub separator: ContentStyle,
+    pub warning: ContentStyle,
+    pub graph: ContentStyle,
+    /// What the typed answer was missing.
+    pub missing: ContentStyle,
+    /// What the typed answer had that the card doesn't.
+    pub extra: ContentStyle,
+    /// Inline code.
+    pub code: ContentStyle,
+    pub comment: ContentStyle,
+    pub string: ContentStyle,
+    pub number: ContentStyle,
+    pub keyword: ContentStyle,
+    pub macro_name: ContentStyle,
+    pub type_name: ContentStyle,
+}
+
+fn fg(color: Color) -> ContentStyle {
+    let mut style = ContentStyle::new();
+    style.foreground_color = Some(color);
+    style
+}
+
+fn with(style: ContentStyle, attribute: Attribute) -> ContentStyle {
+    let mut style = style;
+    style.attributes.set(attribute);
+    style
+}
+
+fn plain() -> ContentStyle {
+    ContentStyle::new()
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        if !has_colors() {
+            return Self::high_contrast().without_colors();
+        }
+        match name {
+            ThemeName::Dark => Self::dark(),
+            ThemeName::Light => Self::light(),
+            ThemeName::HighContrast => Self::high_contrast(),
+        }
+    }
+
+    fn dark() -> Self {
+        Self {
+            selection: fg(Color::Blue),
+            highlight: fg(Color::Yellow),
+            status_bar: fg(Color::Cyan),
+            front: plain(),
+            back: plain(),
+            separator: fg(Color::Blue),
+            warning: with(fg(Color::Yellow), Attribute::Bold),
+            graph: fg(Color::Green),
+            missing: fg(Color::Green),
+            extra: fg(Color::Red),
+            code: fg(Color::Cyan),
+            comment: fg(Color::DarkGrey),
+            string: fg(Color::Green),
+            number: fg(Color::Yellow),
+            keyword: with(fg(Color::Magenta), Attribute::Bold),
+            macro_name: fg(Color::Blue),
+            type_name: fg(Color::Cyan),
+        }
+    }
+
+    /// The dark theme with the colors that are hard to read on white swapped for darker ones.
+    fn light() -> Self {
+        Self {
+            selection: fg(Color::DarkBlue),
+            highlight: with(fg(Color::DarkMagenta), Attribute::Bold),
+            status_bar: fg(Color::DarkBlue),
+            front: plain(),
+            back: plain(),
+            separator: fg(Color::DarkBlue),
+            warning: with(fg(Color::DarkRed), Attribute::Bold),
+            graph: fg(Color::DarkGreen),
+            missing: fg(Color::DarkGreen),
+            extra: fg(Color::DarkRed),
+            code: fg(Color::DarkCyan),
+            comment: fg(Color::DarkGrey),
+            string: fg(Color::DarkGreen),
+            number: fg(Color::DarkYellow),
+            keyword: with(fg(Color::DarkMagenta), Attribute::Bold),
+            macro_name: fg(Color::DarkBlue),
+            type_name: fg(Color::DarkCyan),
+        }
+    }
+
+    /// Leans on attributes, so it reads the same whatever the background is.
+    fn high_contrast() -> Self {
+        let mut warning = with(fg(Color::Black), Attribute::Bold);
+        warning.background_color = Some(Color::Yellow);
+        Self {
+            selection: with(with(plain(), Attribute::Reverse), Attribute::Bold),
+            highlight: with(with(plain(), Attribute::Underlined), Attribute::Bold),
+            status_bar: with(plain(), Attribute::Reverse),
+            front: with(plain(), Attribute::Bold),
+            back: plain(),
+            separator: with(plain(), Attribute::Bold),
+            warning,
+            graph: with(fg(Color::White), Attribute::Bold),
+            missing: with(fg(Color::Green), Attribute::Underlined),
+            extra: with(fg(Color::Red), Attribute::CrossedOut),
+            code: with(fg(Color::Cyan), Attribute::Bold),
+            comment: with(plain(), Attribute::Italic),
+            string: fg(Color::Green),
+            number: fg(Color::Yellow),
+            keyword: with(fg(Color::Magenta), Attribute::Bold),
+            macro_name: with(fg(Color::Cyan), Attribute::Bold),
+            type_name: fg(Color::Cyan),
+        }
+    }
+
+    fn without_colors(self) -> Self {
+        let strip = |style: ContentStyle| {
+            let mut style = style;
+            style.foreground_color = None;
+            style.background_color = None;
+            style.underline_color = None;
+            style
+        };
+        Self {
+            selection: strip(self.selection),
+            highlight: strip(self.highlight),
+            status_bar: strip(self.status_bar),
+            front: strip(self.front),
+            back: strip(self.back),
+            separator: strip(self.separator),
+            warning: strip(self.warning),
+            graph: strip(self.graph),
+            missing: strip(self.missing),
+            extra: strip(self.extra),
+            code: strip(self.code),
+            comment: strip(self.comment),
+            string: strip(self.string),
+            number: strip(self.number),
+            keyword: strip(self.keyword),
+            macro_name: strip(self.macro_name),
+            type_name: strip(self.type_name),
+        }
+    }
+}
+
+/// See <https://no-color.org>.
+fn has_colors() -> bool {
+    let no_color = std::env::var("NO_COLOR").is_ok_and(|value| !value.is_empty());
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    !no_color && !dumb && available_color_count() >= 8
+}
+
+pub fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| Theme::new(config().theme))
+}
@@ -0,0 +1,187 @@
+//! Journal of the changes made to cards from the tui, so the last ones can be reverted.
+//!
+//! A change is recorded as the contents the touched card files had before it, reverting it
+//! writes those back and removes the files it created.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use speki_backend::card::CardCache;
+use speki_backend::Id;
+
+/// How many changes are kept, the oldest ones are dropped past this.
+const MAX_CHANGES: usize = 100;
+
+static JOURNAL: Mutex<Vec<Change>> = Mutex::new(Vec::new());
+
+pub struct Change {
+    pub description: &'static str,
+    /// The card that was graded, if this was a review.
+    pub reviewed: Option<Id>,
+    /// `None` for files that didn't exist before the change.
+    files: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Change {
+    pub fn new(description: &'static str) -> Self {
+        Self {
+            description,
+            reviewed: None,
+            files: vec![],
+        }
+    }
+
+    /// Saves how the card's file looks right now.
+    pub fn card(self, card: &Id, cache: &mut CardCache) -> Self {
+        let path = cache.get_ref(card).as_path();
+        self.file(path)
+    }
+
+    fn file(mut self, path: PathBuf) -> Self {
+        if !self.files.iter().any(|(saved, _)| saved == &path) {
+            let contents = fs::read_to_string(&path).ok();
+            self.files.push((path, contents));
+        }
+        self
+    }
+
+    /// Saves the card along with its dependencies and dependents, since their files point to it.
+    pub fn with_neighbours(mut self, card: &Id, cache: &mut CardCache) -> Self {
+        let neighbours = cache
+            .dependencies(card)
+            .into_iter()
+            .chain(cache.dependents(card));
+        for neighbour in neighbours.collect::<Vec<_>>() {
+            self = self.card(&neighbour, cache);
+        }
+        self.card(card, cache)
+    }
+
+    pub fn reviewed(mut self, card: Id) -> Self {
+        self.reviewed = Some(card);
+        self
+    }
+
+    /// A file the change made, it's removed on undo.
+    pub fn created(&mut self, path: PathBuf) {
+        if !self.files.iter().any(|(saved, _)| saved == &path) {
+            self.files.push((path, None));
+        }
+    }
+
+    /// Adds the change to the journal, call it once the change has been made.
+    pub fn record(self) {
+        let mut journal = JOURNAL.lock().unwrap();
+        journal.push(self);
+        if journal.len() > MAX_CHANGES {
+            journal.remove(0);
+        }
+    }
+
+    fn revert(&self) -> io::Result<()> {
+        for (path, contents) in &self.files {
+            match contents {
+                Some(contents) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(path, contents)?;
+                }
+                None => {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn pop() -> Option<Change> {
+    JOURNAL.lock().unwrap().pop()
+}
+
+/// Reverts the last recorded change, returning it so the caller can update what it shows.
+///
+/// A change that couldn't be reverted is kept in the journal, so undoing can be tried again.
+pub fn undo(cache: &mut CardCache) -> io::Result<Option<Change>> {
+    let Some(change) = pop() else {
+        return Ok(None);
+    };
+    let reverted = change.revert();
+    // Even a failed revert may have written some of the files.
+    cache.refresh();
+    match reverted {
+        Ok(()) => Ok(Some(change)),
+        Err(e) => {
+            JOURNAL.lock().unwrap().push(change);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("speki-undo-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn reverting_puts_the_files_back() {
+        let dir = temp_path("cards");
+        fs::create_dir_all(&dir).unwrap();
+        let edited = dir.join("edited.toml");
+        let created = dir.join("created.toml");
+        fs::write(&edited, "before").unwrap();
+
+        let mut change = Change::new("edit card").file(edited.clone());
+        fs::write(&edited, "after").unwrap();
+        fs::write(&created, "new").unwrap();
+        change.created(created.clone());
+        // Saving the same file again keeps how it looked first.
+        let change = change.file(edited.clone());
+
+        fs::remove_dir_all(&dir).unwrap();
+        change.revert().unwrap();
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "before");
+        assert!(!created.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reverting_reports_files_it_cant_write() {
+        let not_a_dir = temp_path("not-a-dir");
+        fs::write(&not_a_dir, "").unwrap();
+        let mut change = Change::new("edit card");
+        change
+            .files
+            .push((not_a_dir.join("card.toml"), Some("before".into())));
+
+        assert!(change.revert().is_err());
+        fs::remove_file(&not_a_dir).unwrap();
+    }
+
+    #[test]
+    fn the_journal_pops_the_latest_and_drops_the_oldest() {
+        while pop().is_some() {}
+
+        Change::new("first").record();
+        Change::new("second").record();
+        assert_eq!(pop().unwrap().description, "second");
+        assert_eq!(pop().unwrap().description, "first");
+        assert!(pop().is_none());
+
+        Change::new("oldest").record();
+        for _ in 0..MAX_CHANGES {
+            Change::new("newer").record();
+        }
+        let kept: Vec<_> = std::iter::from_fn(pop)
+            .map(|change| change.description)
+            .collect();
+        assert_eq!(kept.len(), MAX_CHANGES);
+        assert!(!kept.contains(&"oldest"));
+    }
+}